@@ -1,6 +1,6 @@
-use std::{env, fs, path::PathBuf, sync::OnceLock};
+use std::{collections::HashMap, env, fs, path::PathBuf, sync::OnceLock};
 use serde::Deserialize;
-use crate::{extend::ResultExt, Error, Result, DEFAULT_CONF, IME_NAME};
+use crate::{engine::Mixing, extend::ResultExt, keys::Bindings, Error, Result, DEFAULT_CONF, IME_NAME};
 
 
 // use parking_lot::{RwLock, RwLockReadGuard};
@@ -32,7 +32,19 @@ pub struct Conf {
     pub font: Font,
     pub layout: Layout,
     pub color: Color,
-    pub behavior: Behavior
+    pub behavior: Behavior,
+    #[serde(default)]
+    pub keys: Bindings,
+    #[serde(default)]
+    pub providers: Providers,
+    #[serde(default)]
+    pub mixing: Mixing,
+    /// Spellings and the variants (from 1 to 16) used by default, as listed by `~n` in the dictionaries
+    #[serde(default)]
+    pub variants: HashMap<String, u32>,
+    /// Syllables and the spellings of the glyphs preferred for them in cartouches
+    #[serde(default)]
+    pub acrophony: HashMap<String, Vec<String>>,
 }
 
 impl Default for Conf {
@@ -77,14 +89,67 @@ pub struct Color {
 
 #[derive(Deserialize, Debug)]
 pub struct Layout {
-    pub vertical: bool
+    pub vertical: bool,
+    #[serde(default = "Layout::default_page_size")]
+    pub page_size: usize,
+    #[serde(default = "Layout::default_labels")]
+    pub labels: String,
+}
+
+impl Layout {
+    fn default_page_size() -> usize { 5 }
+    fn default_labels() -> String { "1234567890".to_string() }
+
+    /// The number of candidates in a page, limited by the number of labels.
+    pub fn page_size(&self) -> usize {
+        self.page_size.min(self.labels.chars().count()).max(1)
+    }
+
+    /// The index of the candidate labeled by the character.
+    pub fn label_index(&self, ch: char) -> Option<usize> {
+        self.labels.chars().position(|label|label == ch)
+    }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Behavior {
     pub long_pi: bool,
     pub long_glyph: bool,
-    pub cjk_space: bool
+    pub cjk_space: bool,
+    /// Tapping Shift toggles the ASCII mode
+    #[serde(default)]
+    pub shift_tap: bool,
+    /// CapsLock types ASCII with the case inverted instead of disabling the IME
+    #[serde(default)]
+    pub caps_lock_ascii: bool,
+    /// Space separates words and Enter commits the sentence
+    #[serde(default)]
+    pub sentence_mode: bool,
+    /// Punctuators are kept in the composition until committed
+    #[serde(default)]
+    pub compose_puncts: bool,
+    /// Words are committed as soon as they can't be anything else. Not available in the sentence mode
+    #[serde(default)]
+    pub auto_commit: bool,
+    /// Appended to the words committed automatically
+    #[serde(default)]
+    pub auto_commit_separator: String,
+    /// Words likely to follow are shown after a commit
+    #[serde(default)]
+    pub predict: bool,
+    /// Compound glyphs are taken over the words they're made of when segmenting sentences
+    #[serde(default)]
+    pub prefer_compounds: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Providers {
+    /// The providers of computed candidates, in order. None by default
+    #[serde(default)]
+    pub enabled: Vec<String>,
+    /// The prefixes of the providers, keyed by their names. The ones left out are the default ones
+    #[serde(flatten)]
+    pub prefixes: HashMap<String, String>,
 }
 
 #[test]
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+//----------------------------------------------------------------------------
+//
+//  Abbreviations are read from the `.abbr` files next to the dictionaries.
+//  Each line is written as `{abbr} {text}` where the text runs till the end
+//  of the line. They're suggested only when the spelling matches exactly.
+//
+//----------------------------------------------------------------------------
+
+pub(super) fn parse(value: &str, abbrs: &mut HashMap<String, Vec<String>>) {
+    for line in value.lines() {
+        if line.trim().is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let Some((abbr, text)) = line.split_once(char::is_whitespace) else {
+            log::error!("Abbreviation without text: {line}");
+            continue;
+        };
+        let text = text.trim();
+        if text.is_empty() {
+            log::error!("Abbreviation without text: {line}");
+            continue;
+        }
+        abbrs.entry(abbr.to_string()).or_default().push(text.to_string());
+    }
+}
+
+#[test]
+fn test_abbr() {
+    let mut abbrs = HashMap::new();
+    parse("// comment\ntp 󱥬󱥔\nsig jan Ajemi  li toki \n\nsig 󱤑\nnothing", &mut abbrs);
+    assert_eq!(abbrs["tp"], ["󱥬󱥔"]);
+    assert_eq!(abbrs["sig"], ["jan Ajemi  li toki", "󱤑"]);
+    assert!(!abbrs.contains_key("nothing"));
+}
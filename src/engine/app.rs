@@ -0,0 +1,88 @@
+use std::{collections::HashMap, fs, path::Path, time::SystemTime};
+use super::Engine;
+
+//----------------------------------------------------------------------------
+//
+//  The schema last used in each application is remembered, and restored
+//  once the application gets focused again. The memory is kept in apps.toml
+//  so that it's shared by the running applications and survives restarts.
+//  The file is only read again once it's changed.
+//
+//----------------------------------------------------------------------------
+
+impl Engine {
+    /// Switch to the schema last used in the application, if any.
+    /// Returns if the schema is switched.
+    pub fn focus_app(&mut self, app: &str) -> bool {
+        self.app = Some(app.to_string());
+        self.reload_apps();
+        let Some(name) = self.apps.get(app).cloned() else {
+            return false;
+        };
+        name != self.schema_name() && self.switch_schema(&name)
+    }
+
+    /// Remember the current schema for the focused application.
+    pub(super) fn remember_schema(&mut self) {
+        let Some(app) = self.app.clone() else {
+            return;
+        };
+        let name = self.schema_name().to_string();
+        // other applications may have changed the memory since
+        self.reload_apps();
+        self.apps.insert(app, name);
+        if let Some(path) = &self.apps_path {
+            save(path, &self.apps);
+            self.apps_modified = modified(path);
+        }
+    }
+
+    /// Read apps.toml again if it's changed since last read.
+    fn reload_apps(&mut self) {
+        let Some(path) = &self.apps_path else {
+            return;
+        };
+        let modified = modified(path);
+        if modified == self.apps_modified {
+            return;
+        }
+        self.apps = load(path);
+        self.apps_modified = modified;
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata|metadata.modified()).ok()
+}
+
+fn load(path: &Path) -> HashMap<String, String> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    toml::from_str(&text).unwrap_or_else(|e|{
+        log::error!("Failed to parse '{}'. {e}", path.display());
+        HashMap::new()
+    })
+}
+
+fn save(path: &Path, apps: &HashMap<String, String>) {
+    let saved = toml::to_string(apps).map_err(|e|e.to_string())
+        .and_then(|text|fs::write(path, text).map_err(|e|e.to_string()));
+    if let Err(e) = saved {
+        log::error!("Failed to save '{}'. {e}", path.display());
+    }
+}
+
+#[test]
+fn test_app() {
+    let mut engine = Engine::default();
+    assert!(!engine.focus_app("chat.exe"));
+    engine.next_schema();
+    assert_eq!(engine.schema_name(), "emoji");
+    assert!(!engine.focus_app("editor.exe"));
+    engine.select_schema("sitelen");
+    assert!(engine.focus_app("chat.exe"));
+    assert_eq!(engine.schema_name(), "emoji");
+    assert!(engine.focus_app("editor.exe"));
+    assert_eq!(engine.schema_name(), "sitelen");
+}
@@ -0,0 +1,98 @@
+use std::{iter::Peekable, str::Chars};
+use super::{provider::Provider, Engine};
+
+//----------------------------------------------------------------------------
+//
+//  A tiny calculator. Expressions typed after the calc prefix (`=` by
+//  default) like `=12*(3+4)` are evaluated with the usual precedence.
+//
+//----------------------------------------------------------------------------
+
+pub struct Calc {
+    pub prefix: String,
+}
+
+impl Provider for Calc {
+    fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    fn takes(&self, ch: char) -> bool {
+        matches!(ch, '0'..='9' | '.' | '+' | '-' | '*' | '/' | '(' | ')')
+    }
+
+    /// The result, and the expression followed by the result.
+    fn provide(&self, _engine: &Engine, input: &str) -> Vec<String> {
+        let Some(result) = eval(input) else {
+            return Vec::new();
+        };
+        vec![result.clone(), format!("{input}={result}")]
+    }
+}
+
+/// The result of the expression, written without a trailing `.0`.
+fn eval(expr: &str) -> Option<String> {
+    let mut chars = expr.chars().peekable();
+    let value = sum(&mut chars)?;
+    if chars.next().is_some() || !value.is_finite() {
+        return None;
+    }
+    Some(value.to_string())
+}
+
+fn sum(chars: &mut Peekable<Chars>) -> Option<f64> {
+    let mut value = product(chars)?;
+    loop {
+        match chars.peek() {
+            Some('+') => { chars.next(); value += product(chars)? }
+            Some('-') => { chars.next(); value -= product(chars)? }
+            _ => return Some(value)
+        }
+    }
+}
+
+fn product(chars: &mut Peekable<Chars>) -> Option<f64> {
+    let mut value = factor(chars)?;
+    loop {
+        match chars.peek() {
+            Some('*') => { chars.next(); value *= factor(chars)? }
+            Some('/') => { chars.next(); value /= factor(chars)? }
+            _ => return Some(value)
+        }
+    }
+}
+
+fn factor(chars: &mut Peekable<Chars>) -> Option<f64> {
+    match chars.peek()? {
+        '-' => {
+            chars.next();
+            Some(-factor(chars)?)
+        }
+        '(' => {
+            chars.next();
+            let value = sum(chars)?;
+            (chars.next()? == ')').then_some(value)
+        }
+        _ => {
+            let mut number = String::new();
+            while let Some(ch) = chars.peek().copied().filter(|ch|ch.is_ascii_digit() || *ch == '.') {
+                number.push(ch);
+                chars.next();
+            }
+            number.parse().ok()
+        }
+    }
+}
+
+#[test]
+fn test_calc() {
+    assert_eq!(eval("1+2*3").as_deref(), Some("7"));
+    assert_eq!(eval("12*(3+4)").as_deref(), Some("84"));
+    assert_eq!(eval("-3/2").as_deref(), Some("-1.5"));
+    assert_eq!(eval("1+"), None);
+    assert_eq!(eval("(1"), None);
+    assert_eq!(eval("1/0"), None);
+    let engine = Engine::default();
+    let calc = Calc { prefix: "=".to_string() };
+    assert_eq!(calc.provide(&engine, "2+2"), ["4", "2+2=4"]);
+}
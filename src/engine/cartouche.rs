@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use crate::conf;
+use super::{schema::Candidate::{self, *}, Engine, Suggestion};
+
+//----------------------------------------------------------------------------
+//
+//  Proper names typed in Latin letters with a leading capital, like `Kanata`,
+//  are written as cartouches of acrophonic glyphs, namely the glyphs whose
+//  words start with the same syllables (ka-na-ta -> kala nasin tawa, etc).
+//
+//----------------------------------------------------------------------------
+
+/// How many cartouches are suggested for a name at most
+const CARTOUCHE_NUM: usize = 5;
+
+impl Engine {
+    /// Cartouches for a capitalized name. The first one takes the preferred glyph of every syllable
+    /// and the others swap the glyphs of the syllables for the less preferred ones.
+    pub(super) fn suggest_cartouches(&self, spelling: &str) -> Vec<Suggestion> {
+        if !spelling.starts_with(|ch: char|ch.is_ascii_uppercase()) {
+            return Vec::new();
+        }
+        let Some(syllables) = syllables(&adapt(spelling)).filter(|syllables|!syllables.is_empty()) else {
+            return Vec::new();
+        };
+        let Some(choices) = syllables.iter()
+            .map(|syllable|Some(self.acrophones(syllable)).filter(|glyphs|!glyphs.is_empty()))
+            .collect::<Option<Vec<_>>>() else {
+            return Vec::new();
+        };
+        let preferred: Vec<&str> = choices.iter().map(|glyphs|glyphs[0]).collect();
+        let mut cartouches = vec![preferred.clone()];
+        let max_len = choices.iter().map(Vec::len).max().unwrap_or(0);
+        'outer: for rank in 1..max_len {
+            for (index, glyphs) in choices.iter().enumerate() {
+                if cartouches.len() >= CARTOUCHE_NUM {
+                    break 'outer;
+                }
+                if let Some(glyph) = glyphs.get(rank) {
+                    let mut cartouche = preferred.clone();
+                    cartouche[index] = glyph;
+                    cartouches.push(cartouche);
+                }
+            }
+        }
+        let (open, close) = (self.remap('['), self.remap(']'));
+        cartouches.into_iter()
+            .map(|glyphs|{
+                let output = format!("{open}{}{close}", glyphs.concat());
+                Suggestion { output: output.clone(), groupping: vec![spelling.len()], words: vec![output] }
+            })
+            .collect()
+    }
+
+    /// Glyphs for a syllable, the ones in `[acrophony]` of conf.toml first.
+    /// A closing `n` is dropped if no glyph starts with it.
+    fn acrophones(&self, syllable: &str) -> Vec<&str> {
+        let configured = conf::get().acrophony.get(syllable).into_iter().flatten()
+            .filter_map(|spelling|match self.schema().candis.get(spelling) {
+                Some(Exact(word, _)) => Some(word.as_str()),
+                _ => None
+            });
+        let derived = self.schema().acrophones.get(syllable).into_iter().flatten().map(String::as_str);
+        let mut glyphs = Vec::new();
+        for glyph in configured.chain(derived) {
+            if !glyphs.contains(&glyph) {
+                glyphs.push(glyph);
+            }
+        }
+        match syllable.strip_suffix('n') {
+            Some(open) if glyphs.is_empty() && !open.is_empty() => self.acrophones(open),
+            _ => glyphs
+        }
+    }
+}
+
+/// Index the words by the first syllables of their spellings.
+/// The words are sorted so the ones in the core range of UCSUR come before the private ones.
+pub(super) fn acrophones(candis: &HashMap<String, Candidate>) -> HashMap<String, Vec<String>> {
+    let mut acrophones: HashMap<String, Vec<String>> = HashMap::new();
+    for (spelling, candi) in candis {
+        let Exact(word, _) = candi else {
+            continue;
+        };
+        let Some(first) = syllables(spelling).and_then(|syllables|syllables.into_iter().next()) else {
+            continue;
+        };
+        acrophones.entry(first).or_default().push(word.clone());
+    }
+    for words in acrophones.values_mut() {
+        words.sort();
+        words.dedup();
+    }
+    acrophones
+}
+
+/// Split a word into syllables of the form (C)V(n). Fails on anything else.
+fn syllables(word: &str) -> Option<Vec<String>> {
+    let chars: Vec<char> = word.chars().collect();
+    let vowel_at = |i: usize|chars.get(i).copied().is_some_and(is_vowel);
+    let mut syllables = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let mut syllable = String::new();
+        if is_consonant(chars[i]) && vowel_at(i + 1) {
+            syllable.push(chars[i]);
+            i += 1;
+        }
+        if !vowel_at(i) {
+            return None;
+        }
+        syllable.push(chars[i]);
+        i += 1;
+        if chars.get(i) == Some(&'n') && !vowel_at(i + 1) {
+            syllable.push('n');
+            i += 1;
+        }
+        syllables.push(syllable);
+    }
+    Some(syllables)
+}
+
+/// Bring a Latin name closer to Toki Pona. Letters are replaced by the closest ones,
+/// and consonants that can't start or close a syllable are dropped.
+fn adapt(name: &str) -> String {
+    let letters: Vec<char> = name.chars()
+        .filter_map(|ch|match ch.to_ascii_lowercase() {
+            'b' | 'f' => Some('p'),
+            'c' | 'g' | 'q' | 'x' => Some('k'),
+            'd' => Some('t'),
+            'r' => Some('l'),
+            'v' => Some('w'),
+            'y' => Some('j'),
+            'z' => Some('s'),
+            'h' => None,
+            ch @ 'a'..='z' => Some(ch),
+            _ => None
+        })
+        .collect();
+    letters.iter().enumerate()
+        .filter(|(i, ch)|{
+            let opens = letters.get(i + 1).copied().is_some_and(is_vowel);
+            let closes = **ch == 'n' && *i > 0 && is_vowel(letters[i - 1]);
+            is_vowel(**ch) || opens || closes
+        })
+        .map(|(_, ch)|*ch)
+        .collect()
+}
+
+fn is_vowel(ch: char) -> bool {
+    matches!(ch, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+fn is_consonant(ch: char) -> bool {
+    matches!(ch, 'j' | 'k' | 'l' | 'm' | 'n' | 'p' | 's' | 't' | 'w')
+}
+
+#[test]
+fn test_cartouche() {
+    let syllables = |word: &str|syllables(word).unwrap().join("-");
+    assert_eq!(syllables("kanata"), "ka-na-ta");
+    assert_eq!(syllables("anpa"), "an-pa");
+    assert_eq!(syllables("anna"), "an-na");
+    assert_eq!(adapt("Kristin"), "litin");
+    assert_eq!(adapt("Berlin"), "pelin");
+    let engine = Engine::default();
+    let suggs: Vec<Suggestion> = engine.suggest("Kanata").collect();
+    let (open, close) = (engine.remap('['), engine.remap(']'));
+    assert_eq!(suggs[0].output, format!("{open}󱤔󱤾󱥨{close}"));
+    assert_eq!(suggs[0].groupping, [6]);
+    assert!(suggs.len() > 1 && suggs.len() <= CARTOUCHE_NUM);
+    assert!(engine.suggest("kanata").all(|sugg|!sugg.output.starts_with(open)));
+}
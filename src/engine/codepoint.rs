@@ -0,0 +1,45 @@
+use super::{provider::Provider, Engine};
+
+//----------------------------------------------------------------------------
+//
+//  Any character can be typed by its code point after the codepoint prefix
+//  (`u+` by default), like `u+F1990`. It's previewed as the only candidate.
+//
+//----------------------------------------------------------------------------
+
+pub struct Codepoint {
+    pub prefix: String,
+}
+
+impl Provider for Codepoint {
+    fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    fn takes(&self, ch: char) -> bool {
+        ch.is_ascii_hexdigit()
+    }
+
+    /// The character of the code point, if it's a valid one.
+    fn provide(&self, _engine: &Engine, input: &str) -> Vec<String> {
+        Some(input)
+            .filter(|hex|(1..=6).contains(&hex.len()))
+            .and_then(|hex|u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32)
+            .map(|ch|ch.to_string())
+            .into_iter()
+            .collect()
+    }
+}
+
+#[test]
+fn test_codepoint() {
+    let engine = Engine::default();
+    let codepoint = Codepoint { prefix: "u+".to_string() };
+    assert_eq!(codepoint.provide(&engine, "F1990"), ["\u{F1990}"]);
+    assert_eq!(codepoint.provide(&engine, "f1997"), ["\u{F1997}"]);
+    // surrogates and numbers too large are no characters
+    assert!(codepoint.provide(&engine, "D800").is_empty());
+    assert!(codepoint.provide(&engine, "1100000").is_empty());
+    assert!(codepoint.provide(&engine, "").is_empty());
+}
@@ -0,0 +1,133 @@
+use std::cmp::Reverse;
+use super::long_glyph::{ext_boundary, ext_control};
+use super::{Engine, Suggestion};
+
+//----------------------------------------------------------------------------
+//
+//  The text before the caret, either told by the client or remembered from
+//  the previous commits. It decides the direction of quotes, continues the
+//  long glyphs and ranks the words by what usually follows the previous one.
+//
+//----------------------------------------------------------------------------
+
+/// Only the end of the context matters.
+pub const CONTEXT_LEN: usize = 64;
+
+impl Engine {
+    pub fn set_context(&mut self, before: &str) {
+        self.context.clear();
+        self.context.push_str(tail(before, CONTEXT_LEN));
+        self.squote_open = quote_open(&self.context, self.schema().squote);
+        self.dquote_open = quote_open(&self.context, self.schema().dquote);
+    }
+
+    /// Learn which word follows which from the committed text, which then becomes part of the context.
+    pub fn learn(&mut self, text: &str) {
+        let mut prev = self.prev_word();
+        for ch in text.chars() {
+            if self.schema().is_joiner(ch) || ext_control(self.schema(), ch) {
+                continue;
+            }
+            if ext_boundary(self.schema(), ch) {
+                prev = None;
+                continue;
+            }
+            if let Some(prev) = prev {
+                *self.bigrams.entry((prev, ch)).or_default() += 1;
+            }
+            prev = Some(ch);
+        }
+        self.context.push_str(text);
+        let len = tail(&self.context, CONTEXT_LEN).len();
+        self.context.drain(..self.context.len() - len);
+    }
+
+    /// The word right before the caret, if not separated by punctuators or spaces.
+    fn prev_word(&self) -> Option<char> {
+        self.context.chars().rev()
+            .find(|ch|!self.schema().is_joiner(*ch) && !ext_control(self.schema(), *ch))
+            .filter(|ch|!ext_boundary(self.schema(), *ch))
+    }
+
+    /// Words that the spelling may refer to, the ones often following the previous word first.
+    pub(super) fn ranked_words<'a>(&'a self, spelling: &str) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+        let Some(prev) = self.prev_word().filter(|_|!self.bigrams.is_empty()) else {
+            return self.words(spelling);
+        };
+        let mut words: Vec<&str> = self.words(spelling).collect();
+        words.sort_by_key(|word| {
+            let count = word.chars().next().and_then(|first|self.bigrams.get(&(prev, first)));
+            Reverse(count.copied().unwrap_or(0))
+        });
+        Box::new(words.into_iter())
+    }
+}
+
+impl Engine {
+    /// Words likely to follow the previous one, the most frequent first.
+    /// They consume no spelling.
+    pub fn predict(&self, limit: usize) -> Vec<Suggestion> {
+        let Some(prev) = self.prev_word() else {
+            return Vec::new();
+        };
+        let mut nexts: Vec<(char, usize)> = self.bigrams.iter()
+            .filter(|((word, _), _)|*word == prev)
+            .map(|((_, next), count)|(*next, *count))
+            .collect();
+        nexts.sort_by_key(|(next, count)|(Reverse(*count), *next));
+        nexts.into_iter()
+            .take(limit)
+            .map(|(next, _)|{
+                let word = next.to_string();
+                Suggestion { output: word.clone(), groupping: vec![0], words: vec![word] }
+            })
+            .collect()
+    }
+}
+
+/// The last `len` characters at most.
+fn tail(text: &str, len: usize) -> &str {
+    let start = text.char_indices().rev().nth(len - 1).map(|(i, _)|i).unwrap_or(0);
+    &text[start..]
+}
+
+/// A quote is open if the last one is an opening one. Dumb quotes are paired by counting.
+fn quote_open(context: &str, (open, close): (char, char)) -> bool {
+    if open == close {
+        context.chars().filter(|ch|*ch == open).count() % 2 == 1
+    } else {
+        context.chars().rev().find(|ch|*ch == open || *ch == close) == Some(open)
+    }
+}
+
+#[test]
+fn test_context() {
+    let mut engine = Engine::default();
+    let (open, close) = engine.schema().dquote;
+    // quotes
+    engine.set_context(&format!("{open}toki"));
+    assert!(engine.dquote_open);
+    engine.set_context(&format!("{open}toki{close}"));
+    assert!(!engine.dquote_open);
+    // long glyphs
+    engine.long_pi = true;
+    let pi = engine.suggest("pi").next().unwrap().output;
+    let lon = engine.suggest("lon").next().unwrap().output;
+    engine.set_context(&pi);
+    let mut text = lon.clone();
+    engine.continue_long_glyph(&mut text);
+    assert!(text.len() > lon.len() && text.contains(&lon));
+    // bigrams
+    engine.set_context("");
+    let words: Vec<&str> = engine.words("l").collect();
+    let last = words.last().unwrap().to_string();
+    engine.learn(&format!("{pi}{last}"));
+    engine.set_context(&pi);
+    assert_eq!(engine.ranked_words("l").next(), Some(last.as_str()));
+    // predictions
+    let predictions = engine.predict(5);
+    assert_eq!(predictions.len(), 1);
+    assert_eq!(predictions[0].output, last);
+    engine.set_context(&format!("{pi} "));
+    assert!(engine.predict(5).is_empty());
+}
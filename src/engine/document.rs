@@ -0,0 +1,69 @@
+use std::mem;
+use super::Engine;
+
+//----------------------------------------------------------------------------
+//
+//  Each document has its own quotes and context. The ones of the focused
+//  document live in the engine and the others are put aside until focused.
+//
+//----------------------------------------------------------------------------
+
+/// Identifies a document. Any value unique among the living documents will do.
+pub type DocId = usize;
+
+#[derive(Default)]
+pub(super) struct DocState {
+    squote_open: bool,
+    dquote_open: bool,
+    context: String,
+}
+
+impl Engine {
+    /// Switch to another document, or to none of them.
+    pub fn focus(&mut self, doc: Option<DocId>) {
+        if self.doc == doc {
+            return;
+        }
+        let state = doc.and_then(|doc|self.docs.remove(&doc)).unwrap_or_default();
+        let prev = self.swap_doc_state(state);
+        if let Some(prev_doc) = self.doc {
+            self.docs.insert(prev_doc, prev);
+        }
+        self.doc = doc;
+    }
+
+    /// Drop the state of a document that is torn down.
+    pub fn forget(&mut self, doc: DocId) {
+        self.docs.remove(&doc);
+        if self.doc == Some(doc) {
+            self.swap_doc_state(DocState::default());
+        }
+    }
+
+    fn swap_doc_state(&mut self, state: DocState) -> DocState {
+        DocState {
+            squote_open: mem::replace(&mut self.squote_open, state.squote_open),
+            dquote_open: mem::replace(&mut self.dquote_open, state.dquote_open),
+            context: mem::replace(&mut self.context, state.context),
+        }
+    }
+}
+
+#[test]
+fn test_document() {
+    let mut engine = Engine::default();
+    let (open, close) = engine.schema().dquote;
+    engine.focus(Some(1));
+    assert_eq!(engine.remap_punct('"'), open);
+    // a new document starts with no quote open
+    engine.focus(Some(2));
+    assert_eq!(engine.remap_punct('"'), open);
+    engine.focus(None);
+    engine.focus(Some(1));
+    assert_eq!(engine.remap_punct('"'), close);
+    assert_eq!(engine.remap_punct('"'), open);
+    // a document torn down starts over
+    engine.forget(2);
+    engine.focus(Some(2));
+    assert_eq!(engine.remap_punct('"'), open);
+}
@@ -1,73 +1,131 @@
-use crate::conf;
+use super::Engine;
+use super::schema::Schema;
 
-const ALA: char = '󱤂';
-const AWEN: char = '󱤈';
-const KEN: char = '󱤘';
-const KEPEKEN: char = '󱤙';
-const LON: char =  '󱤬';
-const PI: char = '󱥍';
-const TAWA: char = '󱥩';
-const LA: char = '󱤡';
-const KAMA: char = '󱤖';
+//----------------------------------------------------------------------------
+//
+//  Long glyphs are made by inserting the controls declared by the schema
+//  around the words that the schema declares to extend. Schemas declaring
+//  no controls have no long glyphs.
+//
+//----------------------------------------------------------------------------
 
+impl Engine {
+    /// Insert long glyphs as if the text follows the context,
+    /// so that a structure like "pi" left open by an earlier commit continues.
+    pub(super) fn continue_long_glyph(&self, text: &mut String) {
+        let tail = self.open_tail();
+        if tail.is_empty() {
+            return insert_long_glyph(text, self.schema(), self.long_pi, self.long_glyph);
+        }
+        let mut full = format!("{tail}{text}");
+        insert_long_glyph(&mut full, self.schema(), self.long_pi, self.long_glyph);
+        // the structure reaches into the context (like "x la") which can't be changed anymore
+        match full.strip_prefix(tail) {
+            Some(rest) => {
+                text.clear();
+                text.push_str(rest);
+            }
+            None => insert_long_glyph(text, self.schema(), self.long_pi, self.long_glyph)
+        }
+    }
 
-const START_OF_LONG_GLYGH: char = '󱦗';
-const END_OF_LONG_GLYPH: char = '󱦘';
-const START_OF_REVERSE_LONG_GLYGH: char = '󱦚';
-const END_OF_REVERSE_LONG_GLYPH: char = '󱦛';
+    /// The end of the context that is not yet part of any long glyph.
+    pub(super) fn open_tail(&self) -> &str {
+        let start = self.context
+            .char_indices()
+            .rev()
+            .find(|(_, ch)|ext_boundary(self.schema(), *ch))
+            .map(|(i, ch)|i + ch.len_utf8())
+            .unwrap_or(0);
+        &self.context[start..]
+    }
+}
 
-pub(super) fn insert_long_glyph(text: &mut String) {
+/// Where structures can't reach across.
+pub(super) fn ext_boundary(schema: &Schema, ch: char) -> bool {
+    ch.is_whitespace() || ext_control(schema, ch) || ext_stop(schema, ch)
+}
+
+pub(super) fn insert_long_glyph(text: &mut String, schema: &Schema, long_pi: bool, long_glyph: bool) {
+    let declared = &schema.long_glyph;
+    if declared.forward.is_none() && declared.reverse.is_none() {
+        return;
+    }
+    // the glyphs needing the controls left undeclared don't extend, so those are never inserted
+    let (start, end) = declared.forward.unwrap_or_default();
+    let (reverse_start, reverse_end) = declared.reverse.unwrap_or_default();
     let mut output = String::new();
     let mut open = false;
     let mut general_question = None;
     for ch in text.chars() {
+        // punctuators end any structure
+        if ext_stop(schema, ch) {
+            if open {
+                let prev = output.pop().unwrap();
+                if prev != start {
+                    output.push(prev);
+                    output.push(end);
+                }
+                open = false;
+            }
+            general_question = None;
+            output.push(ch);
         // insert reverse long glyph for ala and remember the question
-        if ext_as_ala(ch) {
+        } else if ext_as_ala(schema, long_glyph, ch) {
             let Some(mut prev) = output.pop() else {
                 output.push(ch);
                 continue;
             };
-            if prev == END_OF_LONG_GLYPH || prev == ALA {
+            if prev == end || declared.as_ala.contains(&prev) {
                 output.push(prev);
                 output.push(ch);
                 continue; 
             }
             // ala only closes long glyphs that just begin to form structure like "ken ala ken"
-            if prev == START_OF_LONG_GLYGH {
+            if prev == start {
                 open = false;
                 prev = output.pop().unwrap();
             }
             if open {
+                general_question = Some(prev);
                 output.push(prev);
                 output.push(ch);
             } else {
                 general_question = Some(prev);
-                output.push(START_OF_REVERSE_LONG_GLYGH);
+                output.push(reverse_start);
                 output.push(prev);
-                output.push(END_OF_REVERSE_LONG_GLYPH);
+                output.push(reverse_end);
                 output.push(ch);
             } 
-        // to see if ch is being asked. if so, insert long glyph
+        // to see if ch is being asked. if so, insert long glyph unless it's inside one already
         } else if general_question.is_some() && ch == general_question.unwrap() {
             general_question = None;
-            output.push(START_OF_LONG_GLYGH);
+            if open {
+                output.push(ch);
+            } else {
+                output.push(start);
+                output.push(ch);
+                output.push(end);
+            }
+        // the words right after pi are what it extends over, like "pi ken ala ken"
+        } else if open && ext_left(schema, long_pi, long_glyph, ch) && output.ends_with(start) && opens_pi(schema, long_pi, &output) {
             output.push(ch);
-            output.push(END_OF_LONG_GLYPH);
         // no question, insert ch then open long glyph if needed
-        } else if ext_left(ch) {
+        } else if ext_left(schema, long_pi, long_glyph, ch) {
             // close previous long glyph if needed
             if open {
                 let prev = output.pop().unwrap();
-                if prev != START_OF_LONG_GLYGH {
+                if prev != start {
                     output.push(prev);
-                    output.push(END_OF_LONG_GLYPH);
+                    output.push(end);
                 }
             }
             output.push(ch);
-            output.push(START_OF_LONG_GLYGH);
+            output.push(start);
             open = true;
-        } else if ext_right(ch) {
-            if open {
+        } else if ext_right(schema, long_glyph, ch) {
+            // some only extend over a clause of a single word, like "tenpo" in "tenpo kama" but not "jan li" in "jan li kama"
+            if open || (declared.right_word.contains(&ch) && !single_word(schema, long_glyph, &output)) {
                 output.push(ch);
                 continue;
             }
@@ -77,17 +135,43 @@ pub(super) fn insert_long_glyph(text: &mut String) {
                 let Some(prev) = output.pop() else {
                     break;
                 };
-                // todo 
-                // in theory END_OF_LONG_GLYPH cound suggest the pattern "x ala x"
-                // it needs to be handled separately (the long glyph for x ala x will be canceled)
-                if ext_right(prev) || prev == END_OF_LONG_GLYPH {
+                // "x ala x" is extended over as a whole, its own long glyphs canceled
+                if prev == end {
+                    let question: Vec<char> = output.chars().rev().take(6).collect();
+                    match question[..] {
+                        [answer, a, ala, b, asked, c] if a == start && b == reverse_end && c == reverse_start
+                            && answer == asked && declared.as_ala.contains(&ala) => {
+                            output.truncate(output.len() - question.iter().map(|it|it.len_utf8()).sum::<usize>());
+                            temp.push(answer);
+                            temp.push(ala);
+                            temp.push(asked);
+                        }
+                        _ => {
+                            output.push(prev);
+                            break;
+                        }
+                    }
+                // so is a structure extending right before it, like "tenpo kama" in "tenpo kama la"
+                // for schemas where kama extends, the long glyph of kama canceled
+                } else if ext_right(schema, long_glyph, prev) && temp.is_empty() {
+                    temp.push(prev);
+                    if output.ends_with(reverse_end) {
+                        output.pop();
+                        while let Some(inner) = output.pop() {
+                            if inner == reverse_start {
+                                break;
+                            }
+                            temp.push(inner);
+                        }
+                    }
+                } else if ext_right(schema, long_glyph, prev) || ext_stop(schema, prev) {
                     output.push(prev);
                     break;
-                } else if ext_as_ala(prev) {
+                } else if ext_as_ala(schema, long_glyph, prev) {
                     temp.push(prev);
                     let (a, b, c) = (output.pop(), output.pop(), output.pop());
                     match (a, b, c) {
-                        (Some(END_OF_REVERSE_LONG_GLYPH), Some(prev), Some(START_OF_REVERSE_LONG_GLYGH)) => {
+                        (Some(a), Some(prev), Some(c)) if a == reverse_end && c == reverse_start => {
                             temp.push(prev)
                         },
                         _ => {
@@ -105,14 +189,14 @@ pub(super) fn insert_long_glyph(text: &mut String) {
                 output.push(ch);
                 continue;
             }
-            output.push(START_OF_REVERSE_LONG_GLYGH);
+            output.push(reverse_start);
             loop {
                 let Some(t) = temp.pop() else {
                     break;
                 };
                 output.push(t);
             }
-            output.push(END_OF_REVERSE_LONG_GLYPH);
+            output.push(reverse_end);
             output.push(ch);
         } else {
             output.push(ch);
@@ -120,9 +204,9 @@ pub(super) fn insert_long_glyph(text: &mut String) {
     }
     if open {
         let prev = output.pop().unwrap();
-        if prev != START_OF_LONG_GLYGH {
+        if prev != start {
             output.push(prev);
-            output.push(END_OF_LONG_GLYPH);
+            output.push(end);
         }
     }
     if text.len() != output.len() {
@@ -132,27 +216,102 @@ pub(super) fn insert_long_glyph(text: &mut String) {
 }
 
 
-fn ext_as_ala(ch: char) -> bool {
-    ch == ALA && conf::get().behavior.long_glyph
+/// Punctuators other than joiners and the long glyph controls.
+fn ext_stop(schema: &Schema, ch: char) -> bool {
+    let quotes = [schema.squote.0, schema.squote.1, schema.dquote.0, schema.dquote.1];
+    !schema.is_joiner(ch) && !ext_control(schema, ch) && (quotes.contains(&ch) || schema.puncts.values().any(|it|*it == ch))
 }
 
+/// The characters that make up long glyphs.
+pub(super) fn ext_control(schema: &Schema, ch: char) -> bool {
+    let declared = &schema.long_glyph;
+    [declared.forward, declared.reverse].into_iter().flatten().any(|(start, end)|ch == start || ch == end)
+}
 
-fn ext_left(ch: char) -> bool {
-    match ch {
-        PI => conf::get().behavior.long_pi,
-        AWEN|KEN|KEPEKEN|LON|TAWA => conf::get().behavior.long_glyph,
-        _ => false
-    }
+/// Questions take both long glyphs and reverse long glyphs.
+fn ext_as_ala(schema: &Schema, long_glyph: bool, ch: char) -> bool {
+    let declared = &schema.long_glyph;
+    long_glyph && declared.forward.is_some() && declared.reverse.is_some() && declared.as_ala.contains(&ch)
+}
+
+fn ext_left(schema: &Schema, long_pi: bool, long_glyph: bool, ch: char) -> bool {
+    let declared = &schema.long_glyph;
+    declared.forward.is_some()
+        && ((long_pi && declared.pi.contains(&ch)) || (long_glyph && declared.left.contains(&ch)))
+}
+
+fn ext_right(schema: &Schema, long_glyph: bool, ch: char) -> bool {
+    let declared = &schema.long_glyph;
+    long_glyph && declared.reverse.is_some() && declared.right.contains(&ch)
 }
 
-#[allow(unused)]
-fn ext_right(ch: char) -> bool {
-    match ch {
-        // KAMA is disabled for now because i don't want to handle "tenpo kama la"
-        LA => conf::get().behavior.long_glyph,
-        KAMA => false,
-        _ => false
+/// If the clause at the end of the output is a single word, the words joined by joiners counted as one.
+fn single_word(schema: &Schema, long_glyph: bool, output: &str) -> bool {
+    let mut chars = output.chars().rev();
+    loop {
+        match chars.next() {
+            Some(ch) if !ext_boundary(schema, ch) && !schema.is_joiner(ch) && !ext_right(schema, long_glyph, ch) => (),
+            _ => return false,
+        }
+        match chars.next() {
+            Some(ch) if schema.is_joiner(ch) => continue,
+            Some(ch) => return ext_boundary(schema, ch) || ext_right(schema, long_glyph, ch),
+            None => return true,
+        }
     }
 }
 
+/// If the output ends with pi and the start of its long glyph.
+fn opens_pi(schema: &Schema, long_pi: bool, output: &str) -> bool {
+    let mut chars = output.chars().rev().skip(1);
+    long_pi && chars.next().is_some_and(|ch|schema.long_glyph.pi.contains(&ch))
+}
 
+#[test]
+fn test_long_glyph() {
+    // words are spelled in Latin letters and the controls as they're typed
+    const CASES: &[(&str, &str)] = &[
+        ("lon tomo", "lon ( tomo )"),
+        ("awen lon tomo", "awen lon ( tomo )"),
+        ("jan pi pona mute", "jan pi ( pona mute )"),
+        ("jan pi pona lon tomo", "jan pi ( pona ) lon ( tomo )"),
+        ("lon tomo . mi", "lon ( tomo ) . mi"),
+        ("mi moku la", "{ mi moku } la"),
+        ("mi moku la sina lape la", "{ mi moku } la { sina lape } la"),
+        ("tomo ala", "{ tomo } ala"),
+        ("ken ala ken", "{ ken } ala ( ken )"),
+        ("sina wile ala wile", "sina { wile } ala ( wile )"),
+        ("jan pi ken ala ken", "jan pi ( ken ala ken )"),
+        ("lon tomo ala tomo", "lon ( tomo ala tomo )"),
+        ("ken ala ken la", "{ ken ala ken } la"),
+        ("sina wile ala wile la", "{ sina wile ala wile } la"),
+        ("tenpo kama", "{ tenpo } kama"),
+        ("tenpo kama la", "{ tenpo kama } la"),
+        ("mi kama", "{ mi } kama"),
+        ("tenpo kama la mi kama", "{ tenpo kama } la { mi } kama"),
+        ("jan li kama", "jan li kama"),
+        ("mi kama e ni", "{ mi } kama e ni"),
+    ];
+    let mut engine = Engine::default();
+    let glyphs = |engine: &Engine, text: &str|text.split(' ')
+        .map(|atom|match atom {
+            "(" | ")" | "{" | "}" | "." => engine.remap(atom.chars().next().unwrap()).to_string(),
+            word => engine.glyphs(&[word]).unwrap(),
+        })
+        .collect::<String>();
+    for (spelling, expected) in CASES {
+        let mut text = glyphs(&engine, spelling);
+        insert_long_glyph(&mut text, engine.schema(), true, true);
+        assert_eq!(text, glyphs(&engine, expected), "{spelling}");
+    }
+    // either kind of long glyphs works on its own
+    let schema = Schema::from("lon 󱤬\ntomo 󱥫\nla 󱤡\n@long_glyph ( )\n@ext_left 󱤬\n@ext_right 󱤡");
+    let mut text = "󱥫󱤡󱤬󱥫".to_string();
+    insert_long_glyph(&mut text, &schema, false, true);
+    assert_eq!(text, "󱥫󱤡󱤬(󱥫)");
+    // the emoji schema declares no long glyphs
+    engine.select_schema("emoji");
+    let mut text = "󱤬󱤆".to_string();
+    insert_long_glyph(&mut text, engine.schema(), true, true);
+    assert_eq!(text, "󱤬󱤆");
+}
@@ -0,0 +1,113 @@
+use std::iter;
+use serde::Deserialize;
+use crate::extend::IterStr;
+use super::{schema::{Candidate::*, Schema}, Engine};
+
+//----------------------------------------------------------------------------
+//
+//  In the mixing mode, the words of the other schemas are suggested along
+//  with the ones of the current schema, either after them or interleaved.
+//  Punctuators are still remapped by the current schema only.
+//
+//----------------------------------------------------------------------------
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Mixing {
+    /// Suggest the words of all schemas
+    #[serde(default)]
+    pub enabled: bool,
+    /// Take turns between the schemas instead of suggesting the words of the other schemas after
+    #[serde(default)]
+    pub interleave: bool,
+    /// How many words each of the other schemas suggests at most
+    #[serde(default = "Mixing::default_limit")]
+    pub limit: usize,
+}
+
+impl Mixing {
+    fn default_limit() -> usize { 3 }
+}
+
+impl Default for Mixing {
+    fn default() -> Mixing {
+        Mixing { enabled: false, interleave: false, limit: Mixing::default_limit() }
+    }
+}
+
+impl Engine {
+    /// Words that the spelling may refer to, along with the indices of their schemas.
+    /// Only the current schema is looked up unless mixing.
+    pub(super) fn mixed_words<'a>(&'a self, spelling: &str) -> Box<dyn Iterator<Item = (usize, &'a str)> + 'a> {
+        let current = self.ranked_words(spelling)
+            .flat_map(|word|self.alters(word))
+            .map(|word|(0, word));
+        if !self.mixing.enabled {
+            return Box::new(current);
+        }
+        let mut others = self.schemas.iter().enumerate().skip(1)
+            .map(|(index, schema)|{
+                let words = words_in(schema, spelling)
+                    .flat_map(|word|alters_in(schema, word))
+                    .map(move |word|(index, word));
+                Box::new(words) as Box<dyn Iterator<Item = (usize, &'a str)> + 'a>
+            })
+            .collect::<Vec<_>>();
+        if !self.mixing.interleave {
+            return Box::new(current.chain(others.into_iter().flatten()));
+        }
+        others.insert(0, Box::new(current));
+        let mut turn = 0;
+        Box::new(iter::from_fn(move ||{
+            // take turns and skip the exhausted ones
+            while !others.is_empty() {
+                turn %= others.len();
+                match others[turn].next() {
+                    Some(word) => {
+                        turn += 1;
+                        return Some(word);
+                    }
+                    None => drop(others.remove(turn)),
+                }
+            }
+            None
+        }))
+    }
+}
+
+/// Words of a schema that the spelling may refer to.
+pub(super) fn words_in<'a>(schema: &'a Schema, spelling: &str) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+    match schema.candis.get(spelling) {
+        Some(Exact(word, words)) => 
+            Box::new(iter::once(word.as_str()).chain(words.iter_str())),
+        Some(Unique(word)) => 
+            Box::new(iter::once(word.as_str())),
+        Some(Duplicates(words)) => 
+            Box::new(words.iter_str()),
+        None => 
+            Box::new(iter::empty())
+    }
+}
+
+/// The word itself followed by its alternatives in the schema.
+pub(super) fn alters_in<'a>(schema: &'a Schema, word: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    let alters = schema.alters.get(word).into_iter().flat_map(|alters|alters.iter_str());
+    iter::once(word).chain(alters)
+}
+
+#[test]
+fn test_mixing() {
+    let sitelen = |output: &String|output.starts_with(|ch|ch >= '\u{F0000}');
+    // taking turns
+    let engine = Engine { mixing: Mixing { enabled: true, interleave: true, limit: 1 }, ..Engine::default() };
+    let outputs: Vec<String> = engine.suggest("soweli").take(2).map(|sugg|sugg.output).collect();
+    assert_eq!(outputs, ["󱥢", "🦔"]);
+    let outputs: Vec<String> = engine.suggest("so").map(|sugg|sugg.output).collect();
+    assert!(sitelen(&outputs[0]) && !sitelen(&outputs[1]));
+    // the other schema is out of its turns
+    assert_eq!(outputs.iter().filter(|output|!sitelen(output)).count(), 1);
+    // after the words of the current schema
+    let engine = Engine { mixing: Mixing { enabled: true, interleave: false, limit: 3 }, ..Engine::default() };
+    let outputs: Vec<String> = engine.suggest("so").map(|sugg|sugg.output).collect();
+    assert!(sitelen(&outputs[1]));
+    assert!(outputs.iter().filter(|output|!sitelen(output)).count() <= 3);
+}
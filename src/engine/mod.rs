@@ -1,22 +1,39 @@
+mod abbr;
+mod app;
+mod calc;
+mod cartouche;
+mod codepoint;
+mod context;
+mod document;
 mod long_glyph;
+mod mixing;
+mod numeral;
+mod provider;
 mod sentence;
 mod schema;
+mod time;
 use std::collections::VecDeque;
 use std::path::PathBuf;
+use std::time::SystemTime;
 use std::{env, fs, iter};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+pub use self::context::CONTEXT_LEN;
+pub use self::document::DocId;
+pub use self::mixing::Mixing;
+use self::document::DocState;
+use self::provider::Provider;
 use self::schema::Schema;
 use self::schema::Candidate::*;
-use crate::extend::IterStr;
 use crate::global::IME_NAME;
 use crate::{conf, Result, EMOJI_DICT, SITELEN_DICT};
-use crate::CANDI_NUM;
 
 /// Suggestions from engine
 #[derive(Default, Clone)]
 pub struct Suggestion {
     pub output: String,
     pub groupping: Vec<usize>,
+    /// The output of each group, before long glyphs are inserted
+    pub words: Vec<String>,
 }
 
 /// Engine. A struct to store and query words and punctuators
@@ -24,15 +41,38 @@ pub struct Engine {
     schemas: VecDeque<Schema>,
     squote_open: bool,
     dquote_open: bool,
+    long_pi: bool,
+    long_glyph: bool,
+    /// If punctuators are kept in the spelling and segmented into sentences
+    compose_puncts: bool,
+    /// The text before the caret
+    context: String,
+    /// How many times a word is followed by another
+    bigrams: HashMap<(char, char), usize>,
+    /// Abbreviations and the texts they stand for
+    abbrs: HashMap<String, Vec<String>>,
+    /// The focused document and the put-aside states of the others
+    doc: Option<DocId>,
+    docs: HashMap<DocId, DocState>,
+    /// The providers of computed candidates, in order
+    providers: Vec<Box<dyn Provider>>,
+    mixing: Mixing,
+    /// The variants used instead of the words by default
+    preferred: HashMap<String, String>,
+    /// The focused application and the schemas last used in the applications
+    app: Option<String>,
+    apps: HashMap<String, String>,
+    /// Where the schemas of the applications are saved, if ever, and when it was last read or written
+    apps_path: Option<PathBuf>,
+    apps_modified: Option<SystemTime>,
 }
 
 impl Default for Engine {
     fn default() -> Engine {
-        Engine {
-            schemas: VecDeque::from([Schema::from(SITELEN_DICT), Schema::from(EMOJI_DICT)]),
-            squote_open: false,
-            dquote_open: false
-        }
+        Engine::from_schemas(VecDeque::from([
+            Schema { name: "sitelen".to_string(), ..Schema::from(SITELEN_DICT) },
+            Schema { name: "emoji".to_string(), ..Schema::from(EMOJI_DICT) },
+        ]))
     }
 }
 
@@ -40,6 +80,7 @@ impl Engine {
     pub fn build() -> Result<Engine> {
         let mut schemas = VecDeque::new();
         let mut default_schema = None;
+        let mut abbrs = HashMap::new();
         let path = PathBuf::from(env::var("APPDATA")?).join(IME_NAME).join("dict");
         fs::create_dir_all(&path)?;
         for entry in fs::read_dir(&path)? {
@@ -47,10 +88,18 @@ impl Engine {
             let path = entry.path();
             let file_name = entry.file_name();
             let file_name = file_name.to_string_lossy();
-            if path.is_dir() || !file_name.ends_with(".dict") {
+            if path.is_dir() {
                 continue;
             }
-            let schema = Schema::from(fs::read_to_string(path)?.as_str());
+            if file_name.ends_with(".abbr") {
+                abbr::parse(&fs::read_to_string(path)?, &mut abbrs);
+                continue;
+            }
+            if !file_name.ends_with(".dict") {
+                continue;
+            }
+            let name = file_name.trim_end_matches(".dict").to_string();
+            let schema = Schema { name, ..Schema::from(fs::read_to_string(&path)?.as_str()) };
             if file_name == "sitelen.dict" {
                 default_schema = Some(schema)
             } else {
@@ -60,15 +109,45 @@ impl Engine {
         if let Some(default_schema) = default_schema {
             schemas.push_front(default_schema);
         }
-        if schemas.is_empty() {
+        let apps_path = path.parent().map(|dir|dir.join("apps.toml"));
+        let mut engine = if schemas.is_empty() {
             log::info!("No dictionary found. Creating default ones now.");
             let sitelen_path = path.as_path().join("sitelen.dict");
             let emoji_path = path.join("emoji.dict");
             fs::write(sitelen_path, SITELEN_DICT)?;
             fs::write(emoji_path, EMOJI_DICT)?;
-            return Ok(Engine::default())
+            Engine::default()
+        } else {
+            Engine::from_schemas(schemas)
+        };
+        engine.abbrs = abbrs;
+        engine.apps_path = apps_path;
+        Ok(engine)
+    }
+
+    fn from_schemas(schemas: VecDeque<Schema>) -> Engine {
+        let behavior = &conf::get().behavior;
+        let preferred = schemas.iter().flat_map(|schema|schema.preferred(&conf::get().variants)).collect();
+        Engine {
+            schemas,
+            squote_open: false,
+            dquote_open: false,
+            long_pi: behavior.long_pi,
+            long_glyph: behavior.long_glyph,
+            compose_puncts: behavior.compose_puncts,
+            context: String::new(),
+            bigrams: HashMap::new(),
+            abbrs: HashMap::new(),
+            doc: None,
+            docs: HashMap::new(),
+            providers: provider::enabled(),
+            mixing: conf::get().mixing.clone(),
+            preferred,
+            app: None,
+            apps: HashMap::new(),
+            apps_path: None,
+            apps_modified: None,
         }
-        Ok(Engine { schemas, squote_open: false, dquote_open: false })
     }
 
     pub fn build_or_default() -> Engine {
@@ -85,10 +164,60 @@ impl Engine {
         self.schemas.front().unwrap()
     }
 
+    pub fn schema_name(&self) -> &str {
+        &self.schema().name
+    }
+
+    /// Names of the schemas, the current one first.
+    pub fn schema_names(&self) -> impl Iterator<Item = &str> {
+        self.schemas.iter().map(|schema|schema.name.as_str())
+    }
+
     pub fn next_schema(&mut self) {
         self.schemas.rotate_left(1);
         self.squote_open = false;
         self.dquote_open = false;
+        self.remember_schema();
+    }
+
+    pub fn prev_schema(&mut self) {
+        self.schemas.rotate_right(1);
+        self.squote_open = false;
+        self.dquote_open = false;
+        self.remember_schema();
+    }
+
+    /// Switch to the schema of the name. Returns if there's such a schema.
+    pub fn select_schema(&mut self, name: &str) -> bool {
+        let found = self.switch_schema(name);
+        if found {
+            self.remember_schema();
+        }
+        found
+    }
+
+    fn switch_schema(&mut self, name: &str) -> bool {
+        let Some(index) = self.schemas.iter().position(|schema|schema.name == name) else {
+            return false;
+        };
+        self.schemas.rotate_left(index);
+        self.squote_open = false;
+        self.dquote_open = false;
+        true
+    }
+
+    /// Turn long glyphs off if any kind of them is on. Otherwise turn them back on as configured,
+    /// or turn both kinds on if none is configured.
+    pub fn toggle_long_glyph(&mut self) {
+        if self.long_pi || self.long_glyph {
+            self.long_pi = false;
+            self.long_glyph = false;
+        } else {
+            let behavior = &conf::get().behavior;
+            let configured = behavior.long_pi || behavior.long_glyph;
+            self.long_pi = behavior.long_pi || !configured;
+            self.long_glyph = behavior.long_glyph || !configured;
+        }
     }
 
     pub fn remap_punct(&mut self, punct: char) -> char {
@@ -109,73 +238,182 @@ impl Engine {
                 self.dquote_open = !self.dquote_open;
                 remmaped
             }
-            punct => self.schema().puncts
-                .get(&punct)
-                .copied()
-                .filter(|it| *it != '\u{3000}' || !conf::get().behavior.cjk_space )
-                .unwrap_or(punct)
+            punct => self.remap(punct)
         }
     }
 
-    pub fn suggest(&self, spelling: &str) -> Vec<Suggestion> {
-        if !spelling.is_ascii() {
-            return Vec::new(); 
+    fn remap(&self, punct: char) -> char {
+        self.schema().puncts
+            .get(&punct)
+            .copied()
+            .filter(|it| *it != '\u{3000}' || !conf::get().behavior.cjk_space )
+            .unwrap_or(punct)
+    }
+
+    /// Remap a punctuator kept in the spelling without touching the state of quotes.
+    /// A quote is opened or closed according to the quotes output before it.
+    fn remap_fixed(&self, punct: char, before: &str) -> char {
+        let (quote, open) = match punct {
+            '\'' => (self.schema().squote, self.squote_open),
+            '"' => (self.schema().dquote, self.dquote_open),
+            punct => return self.remap(punct)
+        };
+        let count = before.chars().filter(|ch| *ch == quote.0 || *ch == quote.1).count();
+        if open ^ (count % 2 == 1) { quote.1 } else { quote.0 }
+    }
+
+    /// Flip the state of quotes for the ones kept in the spelling, once they are committed.
+    pub fn pass_quotes(&mut self, spelling: &str) {
+        for ch in spelling.chars() {
+            match ch {
+                '\'' => self.squote_open = !self.squote_open,
+                '"' => self.dquote_open = !self.dquote_open,
+                _ => ()
+            }
         }
-        let mut suggs = Vec::with_capacity(CANDI_NUM);
+    }
+
+    pub fn is_joiner(&self, punct: char) -> bool {
+        self.schema().puncts.get(&punct).is_some_and(|it|self.schema().is_joiner(*it))
+    }
+
+    /// If the schema remaps the punctuator, quotes included.
+    pub fn remaps(&self, punct: char) -> bool {
+        matches!(punct, '\'' | '"') || self.schema().puncts.contains_key(&punct)
+    }
+
+    /// Suggestions ordered from the most likely to the least.
+    /// They are produced lazily thus taking only the first few of them is cheap.
+    pub fn suggest<'a>(&'a self, spelling: &'a str) -> impl Iterator<Item = Suggestion> + 'a {
+        // suggest the computed candidates, and nothing else for the spellings meant for providers
+        let provided = self.suggest_provided(spelling);
+        let spelling = if spelling.is_ascii() && !self.provided(spelling) { spelling } else { "" };
+        // suggest the texts of the abbreviation
+        let abbrs = self.abbrs.get(spelling).into_iter().flatten().map(move |text|Suggestion {
+            output: text.clone(),
+            groupping: vec![spelling.len()],
+            words: vec![text.clone()],
+        });
+        // suggest cartouches for a capitalized name
+        let cartouches = iter::once_with(move ||self.suggest_cartouches(spelling)).flatten();
+        // suggest compound glyphs
+        let compounds = self.schema().compounds.get(spelling).into_iter().flatten().map(move |compound|Suggestion {
+            output: compound.clone(),
+            groupping: vec![spelling.len()],
+            words: vec![compound.clone()],
+        });
         // suggest a sentence
-        if let Some(sugg) = self.suggest_sentence(spelling) {
-            suggs.push(sugg);
-        }
-        // suggest single words
-        let mut remains = CANDI_NUM - suggs.len();
+        let sentence = iter::once_with(move ||self.suggest_sentence(spelling)).flatten();
+        // suggest phrases
+        let phrases = self.phrases(spelling).map(move |phrase|Suggestion {
+            output: phrase.to_string(),
+            groupping: vec![spelling.len()],
+            words: vec![phrase.to_string()],
+        });
+        // suggest single words, the ones of the other schemas as many as the limit
         let mut exclude: HashSet<&str> = HashSet::new();
-        'outer_loop:
-        for to in (1..=spelling.len()).rev() {
-            let slice = &spelling[0..to];
-            let words: &mut dyn Iterator<Item = &str> = match self.schema().candis.get(slice) {
-                Some(Exact(word, words)) => 
-                    &mut iter::once(word.as_str()).chain(words.iter_str()),
-                Some(Unique(word)) => 
-                    &mut iter::once(word.as_str()),
-                Some(Duplicates(words)) => 
-                    &mut words.iter_str(),
-                None => {
-                    continue;
-                }
-            };
+        let mut mixed = vec![0; self.schemas.len()];
+        let words = (1..=spelling.len()).rev()
+            .flat_map(move |to| self.mixed_words(&spelling[0..to]).map(move |(index, word)|(index, word, to)))
+            .filter(move |(_, word, _)| exclude.insert(*word))
+            .filter(move |(index, _, _)| {
+                mixed[*index] += 1;
+                *index == 0 || mixed[*index] <= self.mixing.limit
+            })
+            .map(move |(_, word, to)| self.suggest_word(spelling, word, to));
+        provided.into_iter().chain(abbrs).chain(cartouches).chain(compounds).chain(sentence).chain(phrases).chain(words)
+    }
 
-            for word in words {
-                let words: &mut dyn Iterator<Item = &str> = if let Some(alters) = self.schema().alters.get(word) {
-                    &mut iter::once(word).chain(alters.iter_str())
-                } else {
-                    &mut iter::once(word)
-                };
-                for word in words {
-                    if exclude.contains(word) {
-                        continue;
-                    }
-                    exclude.insert(word);
-                    // append the trailing joiner(s) to the suggestion
-                    let mut output = word.to_string();
-                    let mut to = to;
-                    let bytes = spelling.as_bytes();
-                    for i in to..spelling.len() {
-                        if let Some(joiner) = char::try_from(bytes[i]).ok().and_then(|char|self.schema().puncts.get(&char)).copied() {
-                            output.push(joiner);
-                            to += 1;
-                        } else {
-                            break;
-                        }
-                    }
-                    suggs.push(Suggestion{ output, groupping: vec![to] });
-                    remains -= 1;
-                    if remains <= 0 {
-                        break 'outer_loop;
-                    }
-                }
+    /// Phrases that the spelling is a prefix of, once it goes beyond their first words.
+    fn phrases<'a>(&'a self, spelling: &str) -> impl Iterator<Item = &'a str> + 'a {
+        let key: String = spelling.chars().filter(|ch|*ch != ' ').collect();
+        let len = key.len();
+        let valid = !key.is_empty() && key.bytes().all(|byte|byte.is_ascii_alphabetic());
+        let phrases = &self.schema().phrases;
+        valid.then(move ||phrases.range(key.clone()..).take_while(move |(spelling, _)|spelling.starts_with(&key)))
+            .into_iter()
+            .flatten()
+            .flat_map(|(_, phrases)|phrases)
+            .filter(move |phrase|len > phrase.first_len)
+            .map(|phrase|phrase.output.as_str())
+    }
+
+    /// The word if the spelling can't be anything else,
+    /// namely an exact spelling with neither longer spellings, alternatives nor phrases starting with it.
+    pub fn unambiguous(&self, spelling: &str) -> Option<&str> {
+        let phrased = self.schema().phrases.range(spelling.to_string()..)
+            .take_while(|(key, _)|key.starts_with(spelling))
+            .any(|(key, _)|key.len() > spelling.len());
+        if phrased {
+            return None;
+        }
+        match self.schema().candis.get(spelling) {
+            Some(Exact(word, words)) if words.is_empty() && !self.schema().alters.contains_key(word) =>
+                Some(word.as_str()),
+            _ => None
+        }
+    }
+
+    /// If the best suggestion takes the whole spelling, leaving no trailing characters unrecognized.
+    pub fn recognizes(&self, spelling: &str) -> bool {
+        self.suggest(spelling).next().is_some_and(|sugg|sugg.groupping.last() == Some(&spelling.len()))
+    }
+
+    /// Words that the spelling may refer to.
+    fn words<'a>(&'a self, spelling: &str) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+        mixing::words_in(self.schema(), spelling)
+    }
+
+    /// The word itself followed by its alternatives, the preferred variant first if any.
+    fn alters<'a>(&'a self, word: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let preferred = self.preferred.get(word).map(String::as_str);
+        preferred.into_iter().chain(mixing::alters_in(self.schema(), word).filter(move |alter|Some(*alter) != preferred))
+    }
+
+    /// The variant preferred over the word, or the word itself.
+    fn preferred<'a>(&'a self, word: &'a str) -> &'a str {
+        self.preferred.get(word).map(String::as_str).unwrap_or(word)
+    }
+
+    fn suggest_word(&self, spelling: &str, word: &str, mut to: usize) -> Suggestion {
+        // append the trailing joiner(s) to the suggestion. separators are consumed silently.
+        let mut output = word.to_string();
+        for byte in spelling[to..].bytes() {
+            if byte == b' ' {
+                to += 1;
+                continue;
             }
+            let Some(joiner) = self.schema().puncts.get(&char::from(byte)).copied() else {
+                break;
+            };
+            output.push(joiner);
+            to += 1;
+        }
+        let word = output.clone();
+        if !self.open_tail().is_empty() {
+            self.continue_long_glyph(&mut output);
+        }
+        Suggestion{ output, groupping: vec![to], words: vec![word] }
+    }
+
+    /// Suggestions for a single segment of a sentence, namely the ones consuming the whole segment.
+    pub fn suggest_segment(&self, segment: &str) -> Vec<Suggestion> {
+        self.suggest(segment)
+            .filter(|sugg|sugg.groupping == [segment.len()])
+            .collect()
+    }
+
+    /// Replace the word of a certain segment, keeping the rest of the suggestion.
+    pub fn replace_segment(&self, sugg: &mut Suggestion, index: usize, word: &str) {
+        let Some(old) = sugg.words.get_mut(index) else {
+            return;
+        };
+        old.clear();
+        old.push_str(word);
+        sugg.output = sugg.words.concat();
+        if sugg.words.len() > 1 || !self.open_tail().is_empty() {
+            self.continue_long_glyph(&mut sugg.output);
         }
-        suggs
     }
 }
 
@@ -195,3 +433,60 @@ fn repl() {
         
     }
 }
+
+#[test]
+fn test_unambiguous() {
+    let engine = Engine::default();
+    assert!(engine.unambiguous("moku").is_some());
+    // can still be "toki_pona"
+    assert!(engine.unambiguous("toki").is_none());
+    // can still be "kijetesantakaluz"
+    assert!(engine.unambiguous("kijetesantakalu").is_none());
+    assert!(engine.unambiguous("kije").is_none());
+}
+
+#[test]
+fn test_recognizes() {
+    let engine = Engine::default();
+    assert!(engine.recognizes("tok"));
+    assert!(engine.recognizes("toki a"));
+    // no word is spelled with them
+    assert!(!engine.recognizes("tokd"));
+    assert!(!engine.recognizes("q"));
+}
+
+#[test]
+fn test_phrases() {
+    let engine = Engine::default();
+    let has = |spelling: &str, output: &str| engine.suggest(spelling).any(|sugg|sugg.output == output);
+    assert!(has("tokip", "󱥬󱥔"));
+    assert!(has("toki p", "󱥬󱥔"));
+    assert!(!has("toki", "󱥬󱥔"));
+}
+
+#[test]
+fn test_variants() {
+    let (namako, variant) = ("󱥸", "󱥸\u{FE00}");
+    let engine = Engine::default();
+    assert_eq!(engine.suggest("namako").take(2).map(|sugg|sugg.output).collect::<Vec<_>>(), [namako, variant]);
+    let preferred = HashMap::from([(namako.to_string(), variant.to_string())]);
+    let engine = Engine { preferred, ..Engine::default() };
+    assert_eq!(engine.suggest("namako").take(2).map(|sugg|sugg.output).collect::<Vec<_>>(), [variant, namako]);
+    assert!(engine.suggest("namakoli").next().unwrap().output.starts_with(variant));
+}
+
+#[test]
+fn test_compounds() {
+    let engine = Engine::default();
+    let compound = &engine.schema().compounds["tokipona"][0];
+    assert_eq!(&engine.suggest("tokipona").next().unwrap().output, compound);
+    assert!(engine.suggest("tokip").all(|sugg|&sugg.output != compound));
+}
+
+#[test]
+fn test_abbrs() {
+    let mut engine = Engine::default();
+    abbr::parse("tp 󱥬󱥔", &mut engine.abbrs);
+    assert_eq!(engine.suggest("tp").next().unwrap().output, "󱥬󱥔");
+    assert!(engine.suggest("t").all(|sugg|sugg.output != "󱥬󱥔"));
+}
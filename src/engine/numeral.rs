@@ -0,0 +1,100 @@
+use super::{provider::Provider, Engine};
+
+//----------------------------------------------------------------------------
+//
+//  Numbers typed after the numeral prefix (`#` by default) are written in
+//  Toki Pona, both in the additive system of pu and in nasin nanpa pona,
+//  the positional variant where `ale` multiplies what comes before by 100.
+//
+//----------------------------------------------------------------------------
+
+/// The additive words and their values, from the greatest
+const NUMERALS: &[(&str, u64)] = &[("ale", 100), ("mute", 20), ("luka", 5), ("tu", 2), ("wan", 1)];
+
+/// Numbers beyond are too long to be written in the additive system
+const PU_MAX: u64 = 1000;
+
+pub struct Numeral {
+    pub prefix: String,
+}
+
+impl Provider for Numeral {
+    fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    fn takes(&self, ch: char) -> bool {
+        ch.is_ascii_digit()
+    }
+
+    /// Numbers in glyphs and in Latin letters.
+    fn provide(&self, engine: &Engine, input: &str) -> Vec<String> {
+        let Some(number) = Some(input)
+            .filter(|digits|!digits.is_empty() && digits.bytes().all(|byte|byte.is_ascii_digit()))
+            .and_then(|digits|digits.parse::<u64>().ok()) else {
+            return Vec::new();
+        };
+        let mut texts = Vec::new();
+        if number <= PU_MAX {
+            texts.push(pu(number));
+        }
+        texts.push(pona(number));
+        // both systems are the same below 100
+        texts.dedup();
+        texts.iter()
+            .flat_map(|words|engine.glyphs(words).into_iter().chain(Some(words.join(" "))))
+            .collect()
+    }
+}
+
+/// The additive system of pu, `ale` being 100.
+fn pu(mut number: u64) -> Vec<&'static str> {
+    if number == 0 {
+        return vec!["ala"];
+    }
+    let mut words = Vec::new();
+    for (word, value) in NUMERALS.iter().copied() {
+        while number >= value {
+            words.push(word);
+            number -= value;
+        }
+    }
+    words
+}
+
+/// nasin nanpa pona. The number is split into groups of two digits written additively
+/// and each group is followed by as many `ale` as its place. Empty groups are left out.
+pub(super) fn pona(number: u64) -> Vec<&'static str> {
+    if number == 0 {
+        return vec!["ala"];
+    }
+    let mut groups = Vec::new();
+    let mut rest = number;
+    while rest > 0 {
+        groups.push(rest % 100);
+        rest /= 100;
+    }
+    let mut words = Vec::new();
+    for (place, group) in groups.into_iter().enumerate().rev() {
+        if group == 0 {
+            continue;
+        }
+        words.extend(pu(group));
+        words.extend(std::iter::repeat_n("ale", place));
+    }
+    words
+}
+
+#[test]
+fn test_numeral() {
+    assert_eq!(pu(0), ["ala"]);
+    assert_eq!(pu(28).join(" "), "mute luka tu wan");
+    assert_eq!(pu(100), ["ale"]);
+    assert_eq!(pona(100).join(" "), "wan ale");
+    assert_eq!(pona(1234).join(" "), "luka luka tu ale mute luka luka tu tu");
+    assert_eq!(pona(10005).join(" "), "wan ale ale luka");
+    let engine = Engine::default();
+    let numeral = Numeral { prefix: "#".to_string() };
+    assert_eq!(numeral.provide(&engine, "7"), ["󱤭󱥮", "luka tu"]);
+    assert!(numeral.provide(&engine, "").is_empty());
+}
@@ -0,0 +1,109 @@
+use crate::conf;
+use super::{calc::Calc, codepoint::Codepoint, numeral::Numeral, schema::Candidate::*, time::Time, Engine, Suggestion};
+
+//----------------------------------------------------------------------------
+//
+//  Providers compute the candidates instead of looking them up. Each one is
+//  asked for the spellings starting with its prefix, and such spellings are
+//  never anything else. They're enabled and ordered in [providers].
+//
+//----------------------------------------------------------------------------
+
+pub trait Provider {
+    /// The spellings meant for the provider start with it.
+    fn prefix(&self) -> &str;
+    /// If the character is typed into the spelling after the prefix, instead of picking a candidate
+    /// or being committed. Letters are always typed.
+    fn takes(&self, ch: char) -> bool;
+    /// The outputs for what follows the prefix.
+    fn provide(&self, engine: &Engine, input: &str) -> Vec<String>;
+}
+
+/// The built-in providers and their default prefixes
+const BUILTINS: &[(&str, &str)] = &[("numeral", "#"), ("codepoint", "u+"), ("time", "@"), ("calc", "=")];
+
+/// The built-in providers enabled in conf.toml, in order. An empty prefix turns a provider off.
+pub(super) fn enabled() -> Vec<Box<dyn Provider>> {
+    let conf = &conf::get().providers;
+    conf.enabled.iter()
+        .filter_map(|name|{
+            let Some((_, default)) = BUILTINS.iter().find(|(builtin, _)|builtin == name) else {
+                log::error!("Unknown provider '{name}'.");
+                return None;
+            };
+            let prefix = conf.prefixes.get(name).cloned().unwrap_or_else(||default.to_string());
+            let provider: Box<dyn Provider> = match name.as_str() {
+                "numeral" => Box::new(Numeral { prefix }),
+                "codepoint" => Box::new(Codepoint { prefix }),
+                "time" => Box::new(Time { prefix }),
+                _ => Box::new(Calc { prefix }),
+            };
+            Some(provider)
+        })
+        .filter(|provider|!provider.prefix().is_empty())
+        .collect()
+}
+
+impl Engine {
+    /// The provider that the spelling is meant for, along with what follows the prefix.
+    fn provider<'a>(&self, spelling: &'a str) -> Option<(&dyn Provider, &'a str)> {
+        self.providers.iter()
+            .find_map(|provider|spelling.strip_prefix(provider.prefix()).map(|input|(provider.as_ref(), input)))
+    }
+
+    /// If the spelling is meant for a provider.
+    pub fn provided(&self, spelling: &str) -> bool {
+        self.provider(spelling).is_some()
+    }
+
+    /// If the character is typed into the spelling, either as part of a prefix or as taken by the provider.
+    pub fn takes(&self, spelling: &str, ch: char) -> bool {
+        let mut typed = spelling.to_string();
+        typed.push(ch);
+        self.providers.iter().any(|provider|provider.prefix().starts_with(&typed))
+            || self.provider(spelling).is_some_and(|(provider, _)|provider.takes(ch))
+    }
+
+    pub(super) fn suggest_provided(&self, spelling: &str) -> Vec<Suggestion> {
+        let Some((provider, input)) = self.provider(spelling) else {
+            return Vec::new();
+        };
+        let mut outputs: Vec<String> = Vec::new();
+        for output in provider.provide(self, input) {
+            if !outputs.contains(&output) {
+                outputs.push(output);
+            }
+        }
+        outputs.into_iter()
+            .map(|output|Suggestion { output: output.clone(), groupping: vec![spelling.len()], words: vec![output] })
+            .collect()
+    }
+
+    /// The glyphs of the words, if all of them are in the dictionary.
+    pub(super) fn glyphs(&self, words: &[&str]) -> Option<String> {
+        words.iter()
+            .map(|word|match self.schema().candis.get(*word) {
+                Some(Exact(glyph, _)) => Some(glyph.as_str()),
+                _ => None
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_provider() {
+    let engine = Engine { providers: vec![Box::new(Numeral { prefix: "##".to_string() })], ..Engine::default() };
+    // the prefix is typed on
+    assert!(engine.takes("", '#'));
+    assert!(engine.takes("#", '#'));
+    assert!(!engine.takes("#", '.'));
+    // and so are the digits after it
+    assert!(engine.provided("##7"));
+    assert!(engine.takes("##7", '0'));
+    assert!(!engine.takes("##7", '.'));
+    let suggs: Vec<Suggestion> = engine.suggest("##7").collect();
+    assert_eq!(suggs.len(), 2);
+    assert_eq!(suggs[0].groupping, [3]);
+    // nothing else is suggested
+    assert!(engine.suggest("##").next().is_none());
+}
@@ -1,7 +1,10 @@
-use std::collections::HashMap;
-use log::error;
+use std::collections::{BTreeMap, HashMap};
+use log::{error, warn};
+use super::cartouche;
 use Candidate::*;
 
+const PHRASE_DELIMITER: char = '_';
+
 /// To expain why a certain spelling is mapped to certain word(s)
 #[derive(Debug)]
 pub enum Candidate {
@@ -17,15 +20,74 @@ pub enum Candidate {
     Duplicates(Vec<String>)
 }
 
+/// A phrase like `toki_pona`, which is never part of the segmentation.
+#[derive(Debug)]
+pub struct Phrase {
+    /// The length of the first word, `4` for `toki_pona`
+    pub first_len: usize,
+    pub output: String,
+}
+
+/// How long glyphs are made, as declared by the `@` lines of the dictionary.
+/// Without the controls declared, no long glyph is made at all.
+#[derive(Debug, Default)]
+pub struct LongGlyph {
+    /// The start and the end of long glyphs, `@long_glyph`
+    pub forward: Option<(char, char)>,
+    /// The start and the end of reverse long glyphs, `@reverse_long_glyph`
+    pub reverse: Option<(char, char)>,
+    /// Glyphs extending over the words after them if long pi is on, `@ext_pi`
+    pub pi: Vec<char>,
+    /// Glyphs extending over the words after them, `@ext_left`
+    pub left: Vec<char>,
+    /// Glyphs extending over the words before them, `@ext_right`
+    pub right: Vec<char>,
+    /// Glyphs of `@ext_right` extending only over a single word before them, `@ext_right_word`
+    pub right_word: Vec<char>,
+    /// Glyphs that make questions like "ken ala ken", `@ext_as_ala`
+    pub as_ala: Vec<char>,
+}
+
 /// Stores the dictionary and the remapped punctuators.
 /// The dicitonary is indexed in a special way.
 #[derive(Debug)]
 pub struct Schema {
+    /// Named after the dictionary file
+    pub name: String,
     pub candis: HashMap<String, Candidate>,
     pub alters: HashMap<String, Vec<String>>,
+    /// Phrases indexed by their spellings without delimiters, so they can be looked up by prefixes
+    pub phrases: BTreeMap<String, Vec<Phrase>>,
+    /// Compound glyphs like `toki^pona` indexed by their spellings without joiners
+    pub compounds: HashMap<String, Vec<String>>,
+    /// Words indexed by the first syllables of their spellings, for cartouches of names
+    pub acrophones: HashMap<String, Vec<String>>,
     pub puncts: HashMap<char, char>,
     pub squote: (char, char),
     pub dquote: (char, char),
+    /// Characters gluing the glyphs around them, `@joiners`
+    pub joiners: Vec<char>,
+    pub long_glyph: LongGlyph,
+}
+
+impl Schema {
+    /// The variants preferred over the words, given the spellings and the numbers of the variants.
+    /// Only the variants listed in the dictionary are taken.
+    pub fn preferred(&self, variants: &HashMap<String, u32>) -> HashMap<String, String> {
+        variants.iter()
+            .filter_map(|(spelling, n)|{
+                let Some(Exact(word, _)) = self.candis.get(spelling) else {
+                    return None;
+                };
+                let variant = variant(word, &format!("~{n}"))?;
+                self.alters.get(word)?.contains(&variant).then(||(word.clone(), variant))
+            })
+            .collect()
+    }
+
+    pub fn is_joiner(&self, ch: char) -> bool {
+        self.joiners.contains(&ch)
+    }
 }
 
 //----------------------------------------------------------------------------
@@ -45,7 +107,8 @@ impl<'a> From<&'a str> for Atom<'a> {
         use Atom::*;
         let mut chars = str.chars();
         let first_ch = chars.nth(0).unwrap();
-        if !first_ch.is_alphanumeric() && chars.nth(1).is_none() {
+        // only a single character is a punctuator. `~1` or two glyphs like `󱥬󱥔` are text.
+        if !first_ch.is_alphanumeric() && chars.next().is_none() {
             Punct(first_ch)
         } else if first_ch == '#' {
             match u32::from_str_radix(&str[1..], 16).ok().and_then(|codepoint|char::from_u32(codepoint)) {
@@ -79,9 +142,13 @@ impl From<&str> for Schema {
         use Atom::*;
         let mut candis = HashMap::new();
         let mut alters = HashMap::new();
+        let mut phrases: BTreeMap<String, Vec<Phrase>> = BTreeMap::new();
+        let mut compounds = Vec::new();
         let mut puncts = HashMap::new();
         let mut squote = ('\'', '\'');
         let mut dquote = ('"', '"');
+        let mut joiners = Vec::new();
+        let mut long_glyph = LongGlyph::default();
 
         let mut atoms = Vec::new();
         for list in value.lines() {
@@ -106,6 +173,39 @@ impl From<&str> for Schema {
                 [Punct(punct), Punct(remapped)] => {
                     puncts.insert(punct, remapped);
                 }
+                [Text(directive), ref args @ ..] if directive.starts_with('@') => {
+                    let chars: Option<Vec<char>> = args.iter()
+                        .map(|atom|match atom {
+                            Punct(ch) => Some(*ch),
+                            Text(_) => None
+                        })
+                        .collect();
+                    match (directive, chars.as_deref()) {
+                        ("@joiners", Some(chars)) => joiners.extend(chars),
+                        ("@long_glyph", Some(&[start, end])) => long_glyph.forward = Some((start, end)),
+                        ("@reverse_long_glyph", Some(&[start, end])) => long_glyph.reverse = Some((start, end)),
+                        ("@ext_pi", Some(chars)) => long_glyph.pi.extend(chars),
+                        ("@ext_left", Some(chars)) => long_glyph.left.extend(chars),
+                        ("@ext_right", Some(chars)) => long_glyph.right.extend(chars),
+                        ("@ext_right_word", Some(chars)) => long_glyph.right_word.extend(chars),
+                        ("@ext_as_ala", Some(chars)) => long_glyph.as_ala.extend(chars),
+                        _ => error!("Unrecogniable pattern: {list}")
+                    }
+                }
+                [Text(spelling), ref output @ ..] if spelling.contains(PHRASE_DELIMITER) && !output.is_empty() => {
+                    let first_len = spelling.find(PHRASE_DELIMITER).unwrap();
+                    let output = output.iter().map(|atom|decode(&atom.to_string())).collect();
+                    phrases.entry(spelling.replace(PHRASE_DELIMITER, ""))
+                        .or_default()
+                        .push(Phrase { first_len, output });
+                }
+                [Text(spelling), Punct('='), Text(compound)] => {
+                    compounds.push((spelling.to_string(), compound.to_string()));
+                }
+                [Text(compound)] if compound.contains(|ch: char|!ch.is_alphanumeric()) => {
+                    let spelling = compound.chars().filter(|ch|ch.is_alphanumeric()).collect();
+                    compounds.push((spelling, compound.to_string()));
+                }
                 [Text(spelling), word, .. ] => {
                     // store exact spelling -> word
                     candis.insert(spelling.to_string(), Exact(word.to_string(), Vec::new()));
@@ -127,15 +227,19 @@ impl From<&str> for Schema {
                             }
                         }
                     }
-                    // store word -> alternatives
+                    // store word -> alternatives. `~n` stands for the word followed by the n-th variation selector
                     let word = word.to_string();
                     for alter in atoms.iter().skip(2) {
+                        let alter = match alter {
+                            Text(text) => variant(&word, text).unwrap_or_else(||text.to_string()),
+                            Punct(_) => alter.to_string(),
+                        };
                         match alters.get_mut(&word) {
                             None => { 
-                                alters.insert(word.clone(), vec![alter.to_string()]); 
+                                alters.insert(word.clone(), vec![alter]); 
                             }
                             Some(alters) => { 
-                                alters.push(alter.to_string()); 
+                                alters.push(alter); 
                             }
                         }
                     }
@@ -145,10 +249,79 @@ impl From<&str> for Schema {
                 }
             }
         }
-        Schema {candis, alters, puncts, squote, dquote}
+        // dictionaries written before the declarations still have the ones of UCSUR if made of its glyphs
+        if joiners.is_empty() && long_glyph.forward.is_none() && long_glyph.reverse.is_none() && is_ucsur(&candis) {
+            warn!("No joiners or long glyphs are declared in a dictionary of UCSUR. The ones of UCSUR are taken.");
+            let declarations: String = crate::SITELEN_DICT.lines()
+                .filter(|line|line.starts_with('@'))
+                .flat_map(|line|[line, "\n"])
+                .collect();
+            let ucsur = Schema::from(declarations.as_str());
+            joiners = ucsur.joiners;
+            long_glyph = ucsur.long_glyph;
+        }
+        // compounds are made once the words and the joiners are all known
+        let compounds = compounds.into_iter()
+            .filter_map(|(spelling, compound)|{
+                let output = compose(&compound, &candis, &puncts, &joiners);
+                if output.is_none() {
+                    error!("Unrecogniable compound: {compound}");
+                }
+                output.map(|output|(spelling, output))
+            })
+            .fold(HashMap::new(), |mut compounds: HashMap<String, Vec<String>>, (spelling, output)|{
+                compounds.entry(spelling).or_default().push(output);
+                compounds
+            });
+        let acrophones = cartouche::acrophones(&candis);
+        Schema {name: String::new(), candis, alters, phrases, compounds, acrophones, puncts, squote, dquote, joiners, long_glyph}
     }
 }
 
+/// If the words are the glyphs of UCSUR.
+fn is_ucsur(candis: &HashMap<String, Candidate>) -> bool {
+    candis.values().any(|candi|match candi {
+        Exact(word, _) => word.starts_with(|ch|('\u{F1900}'..='\u{F19FF}').contains(&ch)),
+        _ => false
+    })
+}
+
+/// Turn a compound like `toki^pona` into the glyphs and the joiners between them.
+fn compose(compound: &str, candis: &HashMap<String, Candidate>, puncts: &HashMap<char, char>, joiners: &[char]) -> Option<String> {
+    let glyph = |spelling: &str|match candis.get(spelling) {
+        Some(Exact(word, _)) => Some(word.clone()),
+        _ => None
+    };
+    let mut output = String::new();
+    let mut from = 0;
+    for (at, ch) in compound.char_indices().filter(|(_, ch)|!ch.is_alphanumeric()) {
+        output.push_str(&glyph(&compound[from..at])?);
+        output.push(puncts.get(&ch).copied().filter(|joiner|joiners.contains(joiner))?);
+        from = at + ch.len_utf8();
+    }
+    output.push_str(&glyph(&compound[from..])?);
+    Some(output)
+}
+
+/// The word followed by the n-th variation selector (VS1 to VS16), if the text is `~n`.
+fn variant(word: &str, text: &str) -> Option<String> {
+    let n = text.strip_prefix('~')?.parse::<u32>().ok().filter(|n|(1..=16).contains(n))?;
+    let selector = char::from_u32(0xFE00 + n - 1)?;
+    Some(format!("{word}{selector}"))
+}
+
+/// Decode text like `#F196C#F1954` into the characters. Other text stays as is.
+fn decode(text: &str) -> String {
+    if !text.starts_with('#') {
+        return text.to_string();
+    }
+    text.split('#')
+        .skip(1)
+        .map(|hex|u32::from_str_radix(hex, 16).ok().and_then(char::from_u32))
+        .collect::<Option<String>>()
+        .unwrap_or_else(||text.to_string())
+}
+
 #[test]
 fn test() {
     test_schema(crate::SITELEN_DICT);
@@ -156,6 +329,56 @@ fn test() {
 }
 
 
+#[test]
+fn test_atom() {
+    assert!(matches!(Atom::from("^"), Atom::Punct('^')));
+    assert!(matches!(Atom::from("#F1995"), Atom::Punct('\u{F1995}')));
+    assert!(matches!(Atom::from("~1"), Atom::Text("~1")));
+    assert!(matches!(Atom::from("󱥬󱥔"), Atom::Text("󱥬󱥔")));
+}
+
+#[test]
+fn test_variant() {
+    let schema = Schema::from("namako 󱥸 ~1 ~16 ~17");
+    assert_eq!(schema.alters["󱥸"], ["󱥸\u{FE00}", "󱥸\u{FE0F}", "~17"]);
+    let preferred = schema.preferred(&HashMap::from([("namako".to_string(), 16), ("toki".to_string(), 1)]));
+    assert_eq!(preferred, HashMap::from([("󱥸".to_string(), "󱥸\u{FE0F}".to_string())]));
+}
+
+#[test]
+fn test_compound() {
+    let schema = Schema::from("toki 󱥬\npona 󱥔\ntokipona = toki^pona\ntoki-pona\ntoki-ala\n^ #F1995\n- #200D\n@joiners #F1995 #200D");
+    assert_eq!(schema.compounds["tokipona"], ["󱥬\u{F1995}󱥔", "󱥬\u{200D}󱥔"]);
+    // unknown words
+    assert!(!schema.compounds.contains_key("tokiala"));
+}
+
+#[test]
+fn test_directive() {
+    let schema = Schema::from("@joiners #F1995 #200D\n@long_glyph ( )\n@ext_left 󱥍 󱤬\n@long_glyph (\n@ext_right la");
+    assert_eq!(schema.joiners, ['\u{F1995}', '\u{200D}']);
+    assert_eq!(schema.long_glyph.forward, Some(('(', ')')));
+    assert_eq!(schema.long_glyph.left, ['󱥍', '󱤬']);
+    // malformed ones are dropped
+    assert!(schema.long_glyph.right.is_empty());
+    assert!(schema.long_glyph.reverse.is_none());
+    // dictionaries of UCSUR without any declaration get the ones of UCSUR
+    let schema = Schema::from("toki 󱥬\n- #200D");
+    assert!(schema.is_joiner('\u{200D}'));
+    assert!(schema.long_glyph.forward.is_some());
+    assert!(Schema::from("soweli 🐈\n- #200D").joiners.is_empty());
+}
+
+#[test]
+fn test_phrase() {
+    let schema = Schema::from("toki 󱥬\ntoki_pona #F196C#F1954\njan_pona 󱤑 󱥔");
+    let phrase = &schema.phrases["tokipona"][0];
+    assert_eq!((phrase.first_len, phrase.output.as_str()), (4, "󱥬󱥔"));
+    assert_eq!(schema.phrases["janpona"][0].output, "󱤑󱥔");
+    // phrases are not indexed as words
+    assert!(schema.candis.get("tokip").is_none());
+}
+
 #[allow(unused)]
 fn test_schema(str: &str) {
     let start = std::time::Instant::now();
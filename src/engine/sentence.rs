@@ -1,12 +1,15 @@
-use crate::extend::CharExt;
-use super::{long_glyph::insert_long_glyph, schema::Candidate::*, Engine, Suggestion};
+use crate::conf;
+use super::{schema::Candidate::*, Engine, Suggestion};
 
 #[derive(Default, Clone)]
 struct Sentence {
     output: String,
     groupping: Vec<usize>,
+    words: Vec<String>,
     score: usize,
     wc: u8,
+    /// If the next word is glued to the last one by a joiner
+    glued: bool,
 }
 
 impl Sentence {
@@ -25,21 +28,46 @@ impl Sentence {
     }
 
     fn push_word(&mut self, word: &str, len: usize) {
-        if self.output.chars().last().map(|char|char.is_joiner()).unwrap_or(false) {
+        if self.glued {
             *self.groupping.last_mut().unwrap() += len;
+            self.words.last_mut().unwrap().push_str(word);
         } else {
             self.groupping.push(self.groupping.last().copied().unwrap_or(0) + len);
+            self.words.push(word.to_string());
         }
         self.output.push_str(word);
         self.wc += 1;
+        self.glued = false;
+    }
+
+    /// Spaces separate words. They belong to the previous group but output nothing.
+    fn push_separator(&mut self) {
+        if let Some(last) = self.groupping.last_mut() {
+            *last += 1;
+        } else {
+            self.groupping.push(1);
+            self.words.push(String::new());
+        }
+    }
+
+    /// Punctuators are segments of their own.
+    fn push_punct(&mut self, punct: char) {
+        self.groupping.push(self.groupping.last().copied().unwrap_or(0) + 1);
+        self.words.push(punct.to_string());
+        self.output.push(punct);
+        self.wc += 1;
+        self.glued = false;
     }
 
     fn push_joiner(&mut self, joiner: char) {
         self.output.push(joiner);
+        self.glued = true;
         if let Some(last) = self.groupping.last_mut() {
             *last += 1;
+            self.words.last_mut().unwrap().push(joiner);
         } else {
-            self.groupping.push(1)
+            self.groupping.push(1);
+            self.words.push(joiner.to_string());
         }
     }
 }
@@ -63,8 +91,8 @@ impl Engine {
         let Some(mut best_sent) = best_sent else {
             return None;
         };
-        insert_long_glyph(&mut best_sent.output);
-        Some(Suggestion{output:best_sent.output, groupping: best_sent.groupping})
+        self.continue_long_glyph(&mut best_sent.output);
+        Some(Suggestion{output:best_sent.output, groupping: best_sent.groupping, words: best_sent.words})
     }
     
     fn suggest_sentences(&self, spelling: &str) -> Vec<Sentence> {
@@ -82,11 +110,19 @@ impl Engine {
         sents: &mut Vec<Sentence>
     ) 
     {
-        // push leading joiners into the sentence directly
+        // push leading separators, joiners and punctuators into the sentence directly.
+        // words never span across them since no spelling contains any of them.
         let mut spelling = spelling;
         for (i, byte) in spelling.as_bytes().iter().copied().enumerate() {
-            if let Some(joiner) = char::try_from(spelling.as_bytes()[i]).ok().and_then(|char|self.schema().puncts.get(&char)).copied() {
-                sent.push_joiner(joiner);
+            let ch = char::from(byte);
+            if byte == b' ' {
+                sent.push_separator();
+                continue;
+            } else if self.is_joiner(ch) {
+                sent.push_joiner(self.remap(ch));
+                continue;
+            } else if self.compose_puncts && byte.is_ascii_punctuation() {
+                sent.push_punct(self.remap_fixed(ch, &sent.output));
                 continue;
             } else {
                 spelling = &spelling[i..];
@@ -101,7 +137,15 @@ impl Engine {
         let mut unique_len = 0;
 
         let mut found_unique = false;
+        let prefer_compounds = conf::get().behavior.prefer_compounds;
         for len in (1..=spelling.len()).rev() {
+            // a compound is taken as an exact match if preferred
+            let compound = self.schema().compounds.get(&spelling[..len]).and_then(|compounds|compounds.first());
+            if let Some(compound) = compound.filter(|_|prefer_compounds) {
+                exact = Some(compound.as_str());
+                exact_len = len;
+                break;
+            }
             match self.schema().candis.get(&spelling[..len]) {
                 Some(Exact(word, _)) => {
                     exact = Some(word.as_str());
@@ -126,7 +170,7 @@ impl Engine {
             None
         };
         if let Some(exact) = exact {
-            sent.push_exact(exact, exact_len);
+            sent.push_exact(self.preferred(exact), exact_len);
             self.suggest_sentences_recursive(&spelling[exact_len..], sent, sents)
         }
         if let Some(unique) = unique {
@@ -135,7 +179,7 @@ impl Engine {
             } else {
                 sent
             };
-            sent.push_unique(unique, unique_len);
+            sent.push_unique(self.preferred(unique), unique_len);
             self.suggest_sentences_recursive(&spelling[unique_len..], sent, sents)
         }
         if let Some(extra_sent) = extra_sent {
@@ -166,12 +210,17 @@ fn test() {
         let sent = engine.suggest_sentence(spelling).unwrap().output;
         let mut buf =  String::new();
         for word in expected.split(' ') {
-            buf.push_str(&engine.suggest(word)[0].output)
+            buf.push_str(&engine.suggest(word).next().unwrap().output)
         }
         assert_eq!(sent, buf)
     }
     let engine = Engine::build().unwrap();
     assert_sent(&engine, "lilonsewi", "li lon sewi");
+    assert_sent(&engine, "li lon sewi", "li lon sewi");
+    assert_sent(&engine, "pi ma", "pi ma");
+    // punctuators are segments of their own, if they're kept in the spelling
+    let composing = Engine { compose_puncts: true, ..Engine::build().unwrap() };
+    assert_eq!(composing.suggest_sentence("pilon.mi").unwrap().groupping, [2, 5, 6, 8]);
     assert_sent(&engine,"pimaka", "pi ma");
     assert_sent(&engine,"pimkule", "pimeja kule");
 }
\ No newline at end of file
@@ -0,0 +1,59 @@
+use chrono::{Datelike, Local, Timelike};
+use super::{numeral::pona, provider::Provider, Engine};
+
+//----------------------------------------------------------------------------
+//
+//  The current time and date written in Toki Pona, offered right after the
+//  time prefix (`@` by default). Numbers are in nasin nanpa pona.
+//
+//----------------------------------------------------------------------------
+
+pub struct Time {
+    pub prefix: String,
+}
+
+impl Provider for Time {
+    fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    fn takes(&self, _ch: char) -> bool {
+        false
+    }
+
+    /// The time and the date, in glyphs and in Latin letters.
+    fn provide(&self, engine: &Engine, input: &str) -> Vec<String> {
+        if !input.is_empty() {
+            return Vec::new();
+        }
+        let now = Local::now();
+        let (hour, minute) = (pona(now.hour() as u64), pona(now.minute() as u64));
+        let mut outputs = Vec::new();
+        if let (Some(hour), Some(minute)) = (engine.glyphs(&hour), engine.glyphs(&minute)) {
+            outputs.push(format!("{hour}{}{minute}", engine.remap(':')));
+        }
+        outputs.push(format!("{} : {}", hour.join(" "), minute.join(" ")));
+        let date = date(now.day(), now.month());
+        outputs.extend(engine.glyphs(&date));
+        outputs.push(date.join(" "));
+        outputs
+    }
+}
+
+/// Like `tenpo suno nanpa 18 pi tenpo mun nanpa 10`.
+fn date(day: u32, month: u32) -> Vec<&'static str> {
+    let mut words = vec!["tenpo", "suno", "nanpa"];
+    words.extend(pona(day as u64));
+    words.extend(["pi", "tenpo", "mun", "nanpa"]);
+    words.extend(pona(month as u64));
+    words
+}
+
+#[test]
+fn test_time() {
+    assert_eq!(date(18, 10).join(" "), "tenpo suno nanpa luka luka luka tu wan pi tenpo mun nanpa luka luka");
+    let engine = Engine::default();
+    let time = Time { prefix: "@".to_string() };
+    assert_eq!(time.provide(&engine, "").len(), 4);
+    assert!(time.provide(&engine, "x").is_empty());
+}
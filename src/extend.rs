@@ -59,15 +59,10 @@ impl OsStrExt2 for OsString {
 }
 
 pub trait CharExt {
-    fn is_joiner(self) -> bool;
     fn try_from_utf16(value: u16) -> Result<char, DecodeUtf16Error>;
 }
 
 impl CharExt for char {
-    fn is_joiner(self) -> bool {
-        matches!(self, '\u{F1995}' | '\u{F1996}' | '\u{200D}')
-    }
-    
     fn try_from_utf16(value: u16) -> Result<char, DecodeUtf16Error> {
         char::decode_utf16(std::iter::once(value)).next().unwrap()
     }
@@ -64,8 +64,6 @@ pub const DISPLAY_ATTR_ID: GUID = GUID::from_u128(0xE42647FB_4BF0_4570_9013_7684
 pub const LITE_TRAY_ICON_INDEX: u32 = 0;
 pub const DARK_TRAY_ICON_INDEX: u32 = 1;
 // customization
-pub const CANDI_NUM: usize = 5;
-pub const CANDI_INDEXES: [&str; CANDI_NUM] = ["1", "2", "3", "4", "5"];
 pub const CANDI_INDEX_SUFFIX: &str = ". ";
 pub const CANDI_INDEX_SUFFIX_MONO: &str = ".";
 pub const PREEDIT_DELIMITER: &str = "'";
@@ -0,0 +1,314 @@
+use std::{collections::{HashMap, HashSet}, str::FromStr};
+use serde::Deserialize;
+use strum::{Display, EnumString};
+use Action::*;
+
+//----------------------------------------------------------------------------
+//
+//  Key bindings. Chords like "Ctrl+Shift+N" are mapped to actions in the
+//  [keys] section of conf.toml. Actions left out keep their default chords.
+//
+//----------------------------------------------------------------------------
+
+#[derive(EnumString, Display, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[strum(serialize_all = "snake_case")]
+pub enum Action {
+    NextSchema,
+    PrevSchema,
+    Commit,
+    Release,
+    ReleaseWithSpace,
+    Abort,
+    ToggleAscii,
+    ToggleLongGlyph,
+    PrevPage,
+    NextPage,
+}
+
+const DEFAULT_BINDINGS: &[(Action, &str)] = &[
+    (NextSchema, "Ctrl+Shift+N"),
+    (Commit, "Space"),
+    (Release, "Enter"),
+    (ReleaseWithSpace, "Tab"),
+    (Abort, "Escape"),
+    (PrevPage, "PageUp"),
+    (NextPage, "PageDown"),
+];
+
+/// A key with modifiers. `key` is the virtual-key code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub key: u32,
+}
+
+impl FromStr for Chord {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Chord, String> {
+        let mut chord = Chord { ctrl: false, alt: false, shift: false, key: 0 };
+        let mut names = str.split('+').map(str::trim).peekable();
+        while let Some(name) = names.next() {
+            if names.peek().is_none() {
+                chord.key = key_code(name).ok_or_else(||format!("Unknown key '{name}' in '{str}'."))?;
+                break;
+            }
+            match name.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => chord.ctrl = true,
+                "alt" => chord.alt = true,
+                "shift" => chord.shift = true,
+                _ => return Err(format!("Unknown modifier '{name}' in '{str}'.")),
+            }
+        }
+        Ok(chord)
+    }
+}
+
+/// See https://learn.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes
+fn key_code(name: &str) -> Option<u32> {
+    let name = name.to_ascii_uppercase();
+    let mut chars = name.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return match ch {
+            'A'..='Z' | '0'..='9' => Some(ch as u32),
+            ';' => Some(0xBA),
+            '=' => Some(0xBB),
+            ',' => Some(0xBC),
+            '-' => Some(0xBD),
+            '.' => Some(0xBE),
+            '/' => Some(0xBF),
+            '`' => Some(0xC0),
+            '[' => Some(0xDB),
+            '\\' => Some(0xDC),
+            ']' => Some(0xDD),
+            '\'' => Some(0xDE),
+            _ => None
+        };
+    }
+    if let Some(n) = name.strip_prefix('F').and_then(|n|n.parse::<u32>().ok()) {
+        return (1..=24).contains(&n).then_some(0x6F + n);
+    }
+    let code = match name.as_str() {
+        "BACKSPACE" => 0x08,
+        "TAB" => 0x09,
+        "ENTER" | "RETURN" => 0x0D,
+        "CAPSLOCK" => 0x14,
+        "ESCAPE" | "ESC" => 0x1B,
+        "SPACE" => 0x20,
+        "PAGEUP" => 0x21,
+        "PAGEDOWN" => 0x22,
+        "END" => 0x23,
+        "HOME" => 0x24,
+        "LEFT" => 0x25,
+        "UP" => 0x26,
+        "RIGHT" => 0x27,
+        "DOWN" => 0x28,
+        "INSERT" => 0x2D,
+        "DELETE" => 0x2E,
+        "SEMICOLON" => 0xBA,
+        "EQUAL" => 0xBB,
+        "COMMA" => 0xBC,
+        "MINUS" => 0xBD,
+        "PERIOD" => 0xBE,
+        "SLASH" => 0xBF,
+        "BACKQUOTE" => 0xC0,
+        "LEFTBRACKET" => 0xDB,
+        "BACKSLASH" => 0xDC,
+        "RIGHTBRACKET" => 0xDD,
+        "QUOTE" => 0xDE,
+        _ => return None
+    };
+    Some(code)
+}
+
+/// An action can be bound to a single chord or a list of them. An empty list unbinds the action.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Chords {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Chords {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Chords::One(chord) => vec![chord],
+            Chords::Many(chords) => chords,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(try_from = "HashMap<String, Chords>")]
+pub struct Bindings(HashMap<Chord, Action>);
+
+impl Bindings {
+    /// Shift is ignored for keys below '0' (Space, Enter, Tab, etc.) unless the chord with Shift is bound.
+    pub fn get(&self, chord: &Chord) -> Option<Action> {
+        self.0.get(chord).copied().or_else(||{
+            let unshifted = Chord { shift: false, ..*chord };
+            (chord.shift && chord.key < 0x30).then(||self.0.get(&unshifted).copied()).flatten()
+        })
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings::try_from(HashMap::new()).unwrap()
+    }
+}
+
+impl TryFrom<HashMap<String, Chords>> for Bindings {
+    type Error = String;
+    fn try_from(value: HashMap<String, Chords>) -> Result<Bindings, String> {
+        let mut bindings = HashMap::new();
+        let mut bound = HashSet::new();
+        for (name, chords) in value {
+            let action = Action::from_str(&name).map_err(|_|format!("Unknown action '{name}'."))?;
+            bound.insert(action);
+            for str in chords.into_vec() {
+                let chord = Chord::from_str(&str)?;
+                match bindings.insert(chord, action) {
+                    Some(other) if other != action =>
+                        return Err(format!("'{str}' is bound to both '{other}' and '{action}'.")),
+                    _ => ()
+                }
+            }
+        }
+        // the chords taken by the user override the default ones
+        for (action, str) in DEFAULT_BINDINGS.iter().copied() {
+            if bound.contains(&action) {
+                continue;
+            }
+            let chord = Chord::from_str(str).unwrap();
+            bindings.entry(chord).or_insert(action);
+        }
+        Ok(Bindings(bindings))
+    }
+}
+
+const VK_SHIFT: u32 = 0x10;
+const VK_LSHIFT: u32 = 0xA0;
+const VK_RSHIFT: u32 = 0xA1;
+
+/// Detects Shift being pressed and released with no other key in between.
+/// 
+/// Key events may be reported more than once (by both `OnTestKeyDown` and `OnKeyDown`, etc.)
+/// so feeding the same event twice changes nothing.
+#[derive(Debug, Default)]
+pub struct ShiftTap {
+    state: TapState,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum TapState {
+    #[default]
+    Idle,
+    Pressed,
+    Interrupted,
+    Tapped,
+}
+
+impl ShiftTap {
+    /// `modified` tells if any other modifier (Ctrl, Alt, etc.) is being held.
+    pub fn key_down(&mut self, key: u32, modified: bool) {
+        use TapState::*;
+        self.state = match (is_shift(key), self.state) {
+            // Shift repeats while being held
+            (true, Pressed | Interrupted) => self.state,
+            (true, _) if modified => Interrupted,
+            (true, _) => Pressed,
+            (false, Pressed | Interrupted) => Interrupted,
+            (false, _) => Idle,
+        }
+    }
+
+    /// Returns if Shift has been tapped. The tap is kept until taken.
+    pub fn key_up(&mut self, key: u32) -> bool {
+        use TapState::*;
+        if is_shift(key) {
+            self.state = match self.state {
+                Pressed | Tapped => Tapped,
+                Idle | Interrupted => Idle,
+            }
+        }
+        self.state == Tapped
+    }
+
+    pub fn take(&mut self) -> bool {
+        let tapped = self.state == TapState::Tapped;
+        if tapped {
+            self.state = TapState::Idle;
+        }
+        tapped
+    }
+}
+
+fn is_shift(key: u32) -> bool {
+    matches!(key, VK_SHIFT | VK_LSHIFT | VK_RSHIFT)
+}
+
+#[test]
+fn test_chord() {
+    let chord = Chord::from_str("Ctrl+Shift+N").unwrap();
+    assert_eq!(chord, Chord { ctrl: true, alt: false, shift: true, key: 0x4E });
+    let chord = Chord::from_str("alt + f4").unwrap();
+    assert_eq!(chord, Chord { ctrl: false, alt: true, shift: false, key: 0x73 });
+    assert_eq!(Chord::from_str("PageDown").unwrap().key, 0x22);
+    assert_eq!(Chord::from_str("Ctrl+-").unwrap().key, 0xBD);
+    assert!(Chord::from_str("Hyper+A").is_err());
+    assert!(Chord::from_str("Ctrl+Nothing").is_err());
+}
+
+#[test]
+fn test_bindings() {
+    fn parse(toml: &str) -> Result<Bindings, String> {
+        let value: HashMap<String, Chords> = toml::from_str(toml).unwrap();
+        Bindings::try_from(value)
+    }
+    let space = Chord::from_str("Space").unwrap();
+    let enter = Chord::from_str("Enter").unwrap();
+    let bindings = Bindings::default();
+    assert_eq!(bindings.get(&space), Some(Commit));
+    assert_eq!(bindings.get(&enter), Some(Release));
+    let shift_enter = Chord { shift: true, ..enter };
+    assert_eq!(bindings.get(&shift_enter), Some(Release));
+    // user's chords override the default ones
+    let bindings = parse(r#"commit = ["Enter", "Space"]"#).unwrap();
+    assert_eq!(bindings.get(&space), Some(Commit));
+    assert_eq!(bindings.get(&enter), Some(Commit));
+    // empty lists unbind the actions
+    let bindings = parse(r#"release = []"#).unwrap();
+    assert_eq!(bindings.get(&enter), None);
+    // errors
+    assert!(parse(r#"fly = "Space""#).is_err());
+    assert!(parse(r#"commit = "Spaec""#).is_err());
+    assert!(parse("commit = \"Space\"\nabort = \"Space\"").is_err());
+}
+
+#[test]
+fn test_shift_tap() {
+    let mut tap = ShiftTap::default();
+    // a tap, reported twice
+    tap.key_down(VK_SHIFT, false);
+    tap.key_down(VK_SHIFT, false);
+    assert!(tap.key_up(VK_SHIFT));
+    assert!(tap.key_up(VK_SHIFT));
+    assert!(tap.take());
+    assert!(!tap.take());
+    // Shift + A
+    tap.key_down(VK_SHIFT, false);
+    tap.key_down(0x41, false);
+    tap.key_up(0x41);
+    tap.key_down(VK_SHIFT, false);
+    assert!(!tap.key_up(VK_SHIFT));
+    // Ctrl + Shift
+    tap.key_down(VK_SHIFT, true);
+    assert!(!tap.key_up(VK_SHIFT));
+    // an untaken tap is dropped by the next key
+    tap.key_down(VK_LSHIFT, false);
+    assert!(tap.key_up(VK_LSHIFT));
+    tap.key_down(0x41, false);
+    assert!(!tap.take());
+}
@@ -4,6 +4,7 @@ mod install;
 mod global;
 mod logger;
 mod conf;
+mod keys;
 mod extend;
 mod tsf;
 mod engine;
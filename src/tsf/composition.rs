@@ -1,9 +1,9 @@
-use std::ffi::OsString;
+use std::{env, ffi::OsString};
 use log::{debug, trace};
 use windows::Win32::Foundation::E_FAIL;
 use windows::Win32::UI::TextServices::{ITfComposition, ITfCompositionSink_Impl};
 use windows::core::Result;
-use crate::PREEDIT_DELIMITER;
+use crate::{conf, engine::{Suggestion, CONTEXT_LEN}, PREEDIT_DELIMITER};
 use crate::extend::OsStrExt2;
 use super::{edit_session, TextService, TextServiceInner};
 
@@ -18,6 +18,7 @@ use super::{edit_session, TextService, TextServiceInner};
 impl TextServiceInner {
     // there are only two states: composing or not
     pub fn start_composition(&mut self) -> Result<()> {
+        self.update_context()?;
         let composition = edit_session::start_composition(
             self.tid, self.context()?, &self.interface()?)?;
         self.composition = Some(composition); 
@@ -34,42 +35,122 @@ impl TextServiceInner {
         }
         self.composition = None;
         self.spelling.clear();
+        self.caret = 0;
         self.selected.clear();
         self.suggestions.clear();
+        self.page = 0;
+        self.highlight = 0;
+        self.navigating = false;
+        self.focus = None;
+        self.segment_suggestions.clear();
+        self.predictions.clear();
         self.candidate_list()?.hide();
         Ok(())
     }
 
+    /// Show the words likely to follow what's just been committed, as configured.
+    pub fn predict(&mut self) -> Result<()> {
+        if !conf::get().behavior.predict {
+            return Ok(());
+        }
+        self.predictions = self.engine.predict(page_size());
+        self.assure_candidate_list()?;
+        if self.predictions.is_empty() {
+            self.candidate_list()?.hide();
+        } else {
+            self.candidate_list()?.show(&self.predictions, 0)?;
+        }
+        Ok(())
+    }
+
+    pub fn clear_predictions(&mut self) -> Result<()> {
+        self.predictions.clear();
+        self.candidate_list()?.hide();
+        Ok(())
+    }
+
+    /// Show the name of the current schema at the caret till the next key or focus change.
+    pub fn indicate_schema(&mut self) -> Result<()> {
+        self.assure_candidate_list()?;
+        if let Some((x, y)) = self.get_caret_pos() {
+            self.candidate_list()?.locate(x, y)?;
+        }
+        self.candidate_list()?.show_message(self.engine.schema_name())?;
+        self.indicating = true;
+        Ok(())
+    }
+
+    pub fn hide_indicator(&mut self) -> Result<()> {
+        if self.indicating {
+            self.indicating = false;
+            self.candidate_list()?.hide();
+        }
+        Ok(())
+    }
+
+    /// Switch to the schema last used in the application, and show it if switched.
+    pub fn focus_app(&mut self) -> Result<()> {
+        let app = env::current_exe().ok()
+            .and_then(|exe|exe.file_name().map(|name|name.to_string_lossy().to_lowercase()));
+        if let Some(app) = app {
+            if self.engine.focus_app(&app) {
+                self.indicate_schema()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Tell the engine the text before the caret.
+    /// If the client can't tell, the engine goes on with what's been committed.
+    pub fn update_context(&mut self) -> Result<()> {
+        match edit_session::get_text_before(self.tid, self.context()?, CONTEXT_LEN) {
+            Ok(before) if !before.is_empty() => self.engine.set_context(&before),
+            _ => ()
+        }
+        Ok(())
+    }
+
     fn udpate_preedit(&mut self) -> Result<()> {
         self.preedit.clear();
         self.preedit.push_str(&self.selected);
+        let mut caret = self.preedit.len() + self.caret;
         if self.suggestions.is_empty() {
             self.preedit.push_str(&self.spelling);
         } else {
             let mut from = 0;
             for to in &self.suggestions[0].groupping {
-                self.preedit.push_str(&self.spelling[from..*to]);
-                self.preedit.push_str(PREEDIT_DELIMITER);
+                let group = &self.spelling[from..*to];
+                self.preedit.push_str(group);
+                // groups ending with a separator need no delimiter
+                if !group.ends_with(' ') {
+                    self.preedit.push_str(PREEDIT_DELIMITER);
+                    if *to <= self.caret {
+                        caret += PREEDIT_DELIMITER.len();
+                    }
+                }
                 from = *to;
             }
             if from != self.spelling.len() {
                 self.preedit.push_str(&self.spelling[from..])
-            } else {
+            } else if !self.spelling.ends_with(' ') {
                 self.preedit.pop();
+                caret = caret.min(self.preedit.len());
             }
         }
+        let caret = self.preedit[..caret].encode_utf16().count();
         let range = unsafe { self.composition()?.GetRange()? };
         let text = OsString::from(&self.preedit).wchars();
-        edit_session::set_text(self.tid, self.context()?, range, &text, self.display_attribute.as_ref())
+        edit_session::set_text(self.tid, self.context()?, range, &text, self.display_attribute.as_ref(), Some(caret))
     }
 
     fn update_candidate_list(&mut self) -> Result<()> {
         self.assure_candidate_list()?;
         let candidate_list = self.candidate_list()?;
-        if self.suggestions.is_empty() {
+        let page = self.current_page();
+        if page.is_empty() {
             candidate_list.hide();
         } else {
-            candidate_list.show(&self.suggestions)?;
+            candidate_list.show(page, self.highlight)?;
             if let Some((x, y)) = self.get_pos() {
                 candidate_list.locate(x, y)?;
             }
@@ -78,10 +159,82 @@ impl TextServiceInner {
     }
 
 
+    fn update_suggestions(&mut self) {
+        self.suggestions.clear();
+        self.load_suggestions(0);
+        self.update_focus();
+    }
+
+    /// Take the suggestions up to the page, and one more to tell if there's a next page.
+    /// The rest of them are never produced unless the pages are turned.
+    fn load_suggestions(&mut self, page: usize) {
+        let loaded = self.suggestions.len();
+        let wanted = (page + 1) * page_size() + 1;
+        if loaded < wanted {
+            self.suggestions.extend(self.engine.suggest(&self.spelling).skip(loaded).take(wanted - loaded));
+        }
+    }
+
+    /// The suggestions to choose from, either for the whole spelling or for the focused segment.
+    fn candidates(&self) -> &[Suggestion] {
+        if self.focus.is_some() {
+            &self.segment_suggestions
+        } else {
+            &self.suggestions
+        }
+    }
+
+    fn current_page(&self) -> &[Suggestion] {
+        let candidates = self.candidates();
+        let from = (self.page * page_size()).min(candidates.len());
+        let to = (from + page_size()).min(candidates.len());
+        &candidates[from..to]
+    }
+
+    /// Focus on the segment of the 1st suggestion where the caret is.
+    /// There's no focus when the caret is at the end.
+    fn update_focus(&mut self) {
+        self.focus = None;
+        self.segment_suggestions.clear();
+        self.page = 0;
+        self.highlight = 0;
+        self.navigating = false;
+        let Some(sugg) = self.suggestions.first() else {
+            return;
+        };
+        if sugg.groupping.len() <= 1 {
+            return;
+        }
+        let Some(focus) = sugg.groupping.iter().position(|to|self.caret < *to) else {
+            return;
+        };
+        let to = sugg.groupping[focus];
+        let from = if focus == 0 { 0 } else { sugg.groupping[focus - 1] };
+        let word = &sugg.words[focus];
+        self.segment_suggestions = self.engine.suggest_segment(&self.spelling[from..to]);
+        // highlight the current word of the segment
+        if let Some(index) = self.segment_suggestions.iter().position(|it|it.words.concat() == *word) {
+            self.page = index / page_size();
+            self.highlight = index % page_size();
+        }
+        self.focus = Some(focus);
+    }
+
     fn set_text(&self, text: &str) -> Result<()> {
         let text = OsString::from(text).wchars();
         let range = unsafe { self.composition()?.GetRange()? };
-        edit_session::set_text(self.tid, self.context()?, range, &text, None)
+        edit_session::set_text(self.tid, self.context()?, range, &text, None, None)
+    }
+
+    /// Where the caret is, outside of compositions.
+    fn get_caret_pos(&self) -> Option<(i32, i32)> {
+        let pos = edit_session::get_caret_pos(self.tid, self.context().ok()?).ok()?;
+        if pos.0 <= 0 && pos.1 <= 0 {
+            debug!("Abnormal position: ({}, {})", pos.0, pos.1);
+            None
+        } else {
+            Some(pos)
+        }
     }
 
     fn get_pos(&self) -> Option<(i32, i32)> {
@@ -104,8 +257,9 @@ impl TextServiceInner {
 // calling these function while not composing would cause the program to crash
 impl TextServiceInner {
     pub fn push(&mut self, ch: char) -> Result<()>{
-        self.spelling.push(ch);
-        self.suggestions = self.engine.suggest(&self.spelling);
+        self.spelling.insert(self.caret, ch);
+        self.caret += ch.len_utf8();
+        self.update_suggestions();
         self.udpate_preedit()?;
         self.update_candidate_list()?;
         Ok(())
@@ -113,23 +267,118 @@ impl TextServiceInner {
 
     pub fn pop(&mut self) -> Result<()>{
         // todo pop can be used to revert selection
-        self.spelling.pop();
+        let Some((prev, _)) = self.spelling[..self.caret].char_indices().next_back() else {
+            return Ok(());
+        };
+        self.caret = prev;
+        self.spelling.remove(self.caret);
+        if self.spelling.is_empty() {
+            return self.abort();
+        }
+        self.update_suggestions();
+        self.udpate_preedit()?;
+        self.update_candidate_list()?;
+        Ok(())
+    }
+
+    /// Commit right away if the spelling can only be one word, as configured.
+    pub fn auto_commit(&mut self) -> Result<()> {
+        let behavior = &conf::get().behavior;
+        if !behavior.auto_commit || behavior.sentence_mode || self.caret != self.spelling.len() {
+            return Ok(());
+        }
+        let Some(word) = self.engine.unambiguous(&self.spelling) else {
+            return Ok(());
+        };
+        self.selected.push_str(word);
+        self.selected.push_str(&behavior.auto_commit_separator);
+        self.set_text(&self.selected)?;
+        self.engine.learn(&self.selected);
+        self.end_composition()?;
+        self.predict()
+    }
+
+    /// Separate words with a space. Leading and repeated spaces are ignored.
+    pub fn separate(&mut self) -> Result<()> {
+        let prev = self.spelling[..self.caret].chars().last();
+        let next = self.spelling[self.caret..].chars().next();
+        if prev.is_none_or(|ch|ch == ' ') || next == Some(' ') {
+            return Ok(());
+        }
+        self.push(' ')
+    }
+
+    /// Remove the character after the caret.
+    pub fn delete(&mut self) -> Result<()>{
+        if self.caret >= self.spelling.len() {
+            return Ok(());
+        }
+        self.spelling.remove(self.caret);
         if self.spelling.is_empty() {
             return self.abort();
         }
-        self.suggestions = self.engine.suggest(&self.spelling);
+        self.update_suggestions();
         self.udpate_preedit()?;
         self.update_candidate_list()?;
         Ok(())
     }
 
-    /// Commit the 1st suggestion, keeping the unrecognizable trailing characters
+    /// Move the caret over one character.
+    pub fn step_caret(&mut self, forward: bool) -> Result<()> {
+        self.move_caret(step(&self.spelling, self.caret, forward))
+    }
+
+    /// Move the caret within the spelling.
+    /// The segment where the caret is gets focused and can be revised.
+    pub fn move_caret(&mut self, caret: usize) -> Result<()>{
+        let caret = caret.min(self.spelling.len());
+        if caret == self.caret {
+            return Ok(());
+        }
+        self.caret = caret;
+        self.update_focus();
+        self.udpate_preedit()?;
+        self.update_candidate_list()
+    }
+
+    /// Commit the highlighted suggestion, keeping the unrecognizable trailing characters.
+    /// While revising a segment, the whole sentence is committed instead.
     pub fn commit(&mut self) -> Result<()>{
+        if self.focus.is_some() {
+            self.focus = None;
+            self.page = 0;
+            self.highlight = 0;
+        }
         if self.suggestions.is_empty() {
             self.force_release(' ')
         } else {
-            self.select(0)         
+            self.select(self.highlight)
+        }
+    }
+
+    /// Commit the highlighted suggestion and release the unrecognizable trailing characters,
+    /// so that nothing is left in the composition.
+    pub fn commit_all(&mut self) -> Result<()>{
+        if self.focus.is_some() {
+            self.focus = None;
+            self.page = 0;
+            self.highlight = 0;
         }
+        let Some(sugg) = self.suggestions.get(self.page * page_size() + self.highlight) else {
+            return self.release();
+        };
+        self.selected.push_str(&sugg.output);
+        let last = *sugg.groupping.last().unwrap();
+        self.engine.pass_quotes(&self.spelling[..last]);
+        // only the output is learned, not the spelling released along
+        self.engine.learn(&self.selected);
+        if last != self.spelling.len() {
+            self.selected.push(' ');
+            self.selected.push_str(&self.spelling[last..])
+        }
+        self.set_text(&self.selected)?;
+        self.end_composition()?;
+        self.predict()
     }
 
     /// Commit the 1st suggestion and release the unrecognizable trailing characters.
@@ -140,6 +389,8 @@ impl TextServiceInner {
             let sugg = self.suggestions.get(0).unwrap();
             self.selected.push_str(&sugg.output);
             let last = *sugg.groupping.last().unwrap();
+            self.engine.pass_quotes(&self.spelling[..last]);
+            self.engine.learn(&self.selected);
             if last != self.spelling.len() {
                 self.selected.push(' ');
                 self.selected.push_str(&self.spelling[last..])
@@ -150,31 +401,107 @@ impl TextServiceInner {
         }
     }
 
-    /// Select the desired suggestion by pressing numbers.
+    /// Select the desired suggestion in the current page by pressing numbers.
     pub fn select(&mut self, index: usize) -> Result<()> {
+        if index >= page_size() {
+            return Ok(());
+        }
+        let index = self.page * page_size() + index;
+        if self.focus.is_some() {
+            return self.replace_segment(index);
+        }
         if index >= self.suggestions.len() {
             return Ok(());
         }
         let sugg = self.suggestions.get(index).unwrap();
         let last = *sugg.groupping.last().unwrap();
+        self.engine.pass_quotes(&self.spelling[..last]);
         if last == self.spelling.len() {
-            if self.selected.is_empty() {
-                self.set_text(&sugg.output)?;
-            } else {
-                self.selected.push_str(&sugg.output);
-                self.set_text(&self.selected)?;
-            };
-            self.end_composition()
+            self.selected.push_str(&sugg.output);
+            self.set_text(&self.selected)?;
+            // learn from what's committed
+            self.engine.learn(&self.selected);
+            self.end_composition()?;
+            self.predict()
         } else {
             self.selected.push_str(&sugg.output);
             // TODO strip off the begining instead of re allocate
             self.spelling = self.spelling[last..].to_string(); 
-            self.suggestions = self.engine.suggest(&self.spelling);
+            self.caret = self.caret.saturating_sub(last);
+            self.update_suggestions();
             self.udpate_preedit()?;
             self.update_candidate_list()
         }
     }
 
+    /// Move the highlight across the candidates, turning the pages if needed.
+    /// A focused segment is replaced by the highlighted candidate right away.
+    pub fn move_highlight(&mut self, forward: bool) -> Result<()> {
+        if forward && self.focus.is_none() {
+            self.load_suggestions((self.page * page_size() + self.highlight + 1) / page_size());
+        }
+        let len = self.candidates().len();
+        if len == 0 {
+            return Ok(());
+        }
+        let index = self.page * page_size() + self.highlight;
+        let index = if forward {
+            (index + 1).min(len - 1)
+        } else {
+            index.saturating_sub(1)
+        };
+        // in horizontal layout left and right move the highlight too, until it's back to the 1st one
+        self.navigating = !conf::get().layout.vertical && index != 0;
+        if self.focus.is_some() {
+            self.replace_segment(index)
+        } else {
+            self.page = index / page_size();
+            self.highlight = index % page_size();
+            self.update_candidate_list()
+        }
+    }
+
+    /// Replace the focused segment with the desired suggestion without committing.
+    fn replace_segment(&mut self, index: usize) -> Result<()> {
+        let (Some(focus), Some(sugg)) = (self.focus, self.segment_suggestions.get(index)) else {
+            return Ok(());
+        };
+        let word = sugg.words.concat();
+        self.engine.replace_segment(&mut self.suggestions[0], focus, &word);
+        self.page = index / page_size();
+        self.highlight = index % page_size();
+        self.update_candidate_list()
+    }
+
+    pub fn has_next_page(&self) -> bool {
+        (self.page + 1) * page_size() < self.candidates().len()
+    }
+
+    pub fn has_prev_page(&self) -> bool {
+        self.page > 0
+    }
+
+    pub fn next_page(&mut self) -> Result<()> {
+        if !self.has_next_page() {
+            return Ok(());
+        }
+        self.page += 1;
+        self.highlight = 0;
+        if self.focus.is_none() {
+            self.load_suggestions(self.page);
+        }
+        self.update_candidate_list()
+    }
+
+    pub fn prev_page(&mut self) -> Result<()> {
+        if !self.has_prev_page() {
+            return Ok(());
+        }
+        self.page -= 1;
+        self.highlight = 0;
+        self.update_candidate_list()
+    }
+
     // Release the raw ascii chars
     pub fn release(&mut self) -> Result<()> {
         if self.selected.is_empty() {
@@ -200,8 +527,15 @@ impl TextServiceInner {
         self.end_composition()
     }
 
+    // Drop the spelling. What's been selected stays.
+    pub fn discard(&mut self) -> Result<()> {
+        self.set_text(&self.selected)?;
+        self.end_composition()
+    }
+
     // Interupted. Abort everything.
     pub fn abort(&mut self) -> Result<()> {
+        self.hide_indicator()?;
         if self.selected.is_empty() {
             let _ = self.set_text(&self.spelling);
         } else {
@@ -217,6 +551,19 @@ impl TextServiceInner {
 
 
 
+fn page_size() -> usize {
+    conf::get().layout.page_size()
+}
+
+/// The caret right before or after the character next to it.
+fn step(spelling: &str, caret: usize, forward: bool) -> usize {
+    if forward {
+        spelling[caret..].chars().next().map_or(caret, |ch|caret + ch.len_utf8())
+    } else {
+        spelling[..caret].chars().next_back().map_or(caret, |ch|caret - ch.len_utf8())
+    }
+}
+
 #[allow(non_snake_case)]
 impl ITfCompositionSink_Impl for TextService {
     fn OnCompositionTerminated(&self, _ecwrite:u32, _composition: Option<&ITfComposition>) -> Result<()> {
@@ -228,4 +575,18 @@ impl ITfCompositionSink_Impl for TextService {
         // `self.try_lock()` avoids such issue
         self.try_write()?.abort()
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_step() {
+    // the caret moves over whole characters, so the spelling can be edited where it lands
+    let mut spelling = "pé".to_string();
+    let caret = step(&spelling, spelling.len(), false);
+    assert_eq!(caret, 1);
+    spelling.insert(caret, 'a');
+    assert_eq!(spelling, "paé");
+    let caret = step(&spelling, caret + 1, true);
+    assert_eq!(caret, spelling.len());
+    assert_eq!(step(&spelling, caret, true), caret);
+    assert_eq!(step(&spelling, 0, false), 0);
+}
@@ -1,9 +1,10 @@
 use std::cell::Cell;
 use std::mem::ManuallyDrop;
+use std::ptr;
 use log::{error, trace};
 use windows::Win32::Foundation::{BOOL, FALSE, RECT, S_OK};
 use windows::core::{Interface, implement, AsImpl, Result, VARIANT};
-use windows::Win32::UI::TextServices::{ITfComposition, ITfCompositionSink, ITfContext, ITfContextComposition, ITfEditSession, ITfEditSession_Impl, ITfInsertAtSelection, ITfRange, GUID_PROP_ATTRIBUTE, TF_AE_NONE, TF_ANCHOR_END, TF_ES_READWRITE, TF_IAS_QUERYONLY, TF_SELECTION, TF_ST_CORRECTION};
+use windows::Win32::UI::TextServices::{ITfComposition, ITfCompositionSink, ITfContext, ITfContextComposition, ITfEditSession, ITfEditSession_Impl, ITfInsertAtSelection, ITfRange, GUID_PROP_ATTRIBUTE, TF_AE_NONE, TF_ANCHOR_END, TF_ANCHOR_START, TF_ES_READ, TF_ES_READWRITE, TF_IAS_QUERYONLY, TF_SELECTION, TF_ST_CORRECTION, TF_TF_MOVESTART};
 
 //----------------------------------------------------------------------------
 //
@@ -79,13 +80,15 @@ pub fn end_composition(tid:u32, context: &ITfContext, composition: &ITfCompositi
     }
 }
 
-pub fn set_text(tid:u32, context: &ITfContext, range: ITfRange, text: &[u16], dispaly_attribute: Option<&VARIANT>) -> Result<()> {
+/// `caret` is the position of the cursor within the text. `None` puts the cursor at the end.
+pub fn set_text(tid:u32, context: &ITfContext, range: ITfRange, text: &[u16], dispaly_attribute: Option<&VARIANT>, caret: Option<usize>) -> Result<()> {
     #[implement(ITfEditSession)]
     struct Session<'a> {
         context: &'a ITfContext,
         range: ITfRange,
         text: &'a [u16],
-        dispaly_attribute: Option<&'a VARIANT>
+        dispaly_attribute: Option<&'a VARIANT>,
+        caret: Option<usize>,
     }
 
     impl ITfEditSession_Impl for Session<'_> {
@@ -106,7 +109,12 @@ pub fn set_text(tid:u32, context: &ITfContext, range: ITfRange, text: &[u16], di
                 if self.text.is_empty() {
                     return Ok(())
                 }
-                // move the cursor to the end
+                // move the cursor to the caret or simply the end
+                if let Some(caret) = self.caret {
+                    let mut shifted = 0;
+                    self.range.Collapse(ec, TF_ANCHOR_START)?;
+                    self.range.ShiftEnd(ec, caret as i32, &mut shifted, ptr::null())?;
+                }
                 self.range.Collapse(ec, TF_ANCHOR_END)?;
                 let mut selection = TF_SELECTION::default();
                 selection.range = ManuallyDrop::new(Some(self.range.clone()));
@@ -118,7 +126,7 @@ pub fn set_text(tid:u32, context: &ITfContext, range: ITfRange, text: &[u16], di
         }
     }
 
-    let session = ITfEditSession::from(Session{context, range, text, dispaly_attribute});
+    let session = ITfEditSession::from(Session{context, range, text, dispaly_attribute, caret});
     unsafe {
         let result = context.RequestEditSession(tid, &session, TF_ES_READWRITE)?;
         if result != S_OK {
@@ -166,6 +174,123 @@ pub fn insert_text(tid:u32, context: &ITfContext, text: &[u16]) -> Result<()>{
     }
 }
 
+/// The text before the selection, `len` UTF-16 units at most.
+pub fn get_text_before(tid:u32, context: &ITfContext, len: usize) -> Result<String> {
+    #[implement(ITfEditSession)]
+    struct Session<'a> {
+        context: &'a ITfContext,
+        len: usize,
+        text: Cell<String>,
+    }
+
+    impl ITfEditSession_Impl for Session<'_> {
+        #[allow(non_snake_case)]
+        fn DoEditSession(&self, ec:u32) -> Result<()> {
+            unsafe {
+                let range = self.context.cast::<ITfInsertAtSelection>()?
+                    .InsertTextAtSelection(ec, TF_IAS_QUERYONLY, &[])?;
+                range.Collapse(ec, TF_ANCHOR_START)?;
+                let mut shifted = 0;
+                range.ShiftStart(ec, -(self.len as i32), &mut shifted, ptr::null())?;
+                let mut buf = vec![0; self.len];
+                let mut fetched = 0;
+                range.GetText(ec, 0, &mut buf, &mut fetched)?;
+                self.text.set(String::from_utf16_lossy(&buf[..fetched as usize]));
+                Ok(())
+            }
+        }
+    }
+
+    let session = ITfEditSession::from(Session{context, len, text: Cell::new(String::new())});
+    unsafe {
+        let result = context.RequestEditSession(tid, &session, TF_ES_READ)?;
+        if result != S_OK {
+            Err(result.into())
+        } else {
+            let session: &Session = session.as_impl();
+            Ok(session.text.take())
+        }
+    }
+}
+
+pub fn get_text(tid:u32, context: &ITfContext, range: &ITfRange) -> Result<String> {
+    #[implement(ITfEditSession)]
+    struct Session<'a> {
+        range: &'a ITfRange,
+        text: Cell<String>,
+    }
+
+    impl ITfEditSession_Impl for Session<'_> {
+        #[allow(non_snake_case)]
+        fn DoEditSession(&self, ec:u32) -> Result<()> {
+            unsafe {
+                let range = self.range.Clone()?;
+                let mut text = Vec::new();
+                let mut buf = [0; 64];
+                loop {
+                    let mut fetched = 0;
+                    // reading moves the start of the range forward
+                    range.GetText(ec, TF_TF_MOVESTART, &mut buf, &mut fetched)?;
+                    text.extend_from_slice(&buf[..fetched as usize]);
+                    if (fetched as usize) < buf.len() {
+                        break;
+                    }
+                }
+                self.text.set(String::from_utf16_lossy(&text));
+                Ok(())
+            }
+        }
+    }
+
+    let session = ITfEditSession::from(Session{range, text: Cell::new(String::new())});
+    unsafe {
+        let result = context.RequestEditSession(tid, &session, TF_ES_READ)?;
+        if result != S_OK {
+            Err(result.into())
+        } else {
+            let session: &Session = session.as_impl();
+            Ok(session.text.take())
+        }
+    }
+}
+
+/// The position of the caret, or the start of the selection.
+pub fn get_caret_pos(tid:u32, context: &ITfContext) -> Result<(i32, i32)> {
+    #[implement(ITfEditSession)]
+    struct Session<'a> {
+        context: &'a ITfContext,
+        pos: Cell<(i32, i32)>,
+    }
+
+    impl ITfEditSession_Impl for Session<'_> {
+        #[allow(non_snake_case)]
+        fn DoEditSession(&self, ec:u32) -> Result<()> {
+            unsafe {
+                let range = self.context.cast::<ITfInsertAtSelection>()?
+                    .InsertTextAtSelection(ec, TF_IAS_QUERYONLY, &[])?;
+                range.Collapse(ec, TF_ANCHOR_START)?;
+                let mut rect = RECT::default();
+                let mut clipped = BOOL::default();
+                let view = self.context.GetActiveView()?;
+                view.GetTextExt(ec, &range, &mut rect, &mut clipped)?;
+                self.pos.set((rect.left, rect.bottom));
+                Ok(())
+            }
+        }
+    }
+
+    let session = ITfEditSession::from(Session{context, pos: Cell::new((0, 0))});
+    unsafe {
+        let result = context.RequestEditSession(tid, &session, TF_ES_READ)?;
+        if result != S_OK {
+            Err(result.into())
+        } else {
+            let session: &Session = session.as_impl();
+            Ok(session.pos.take())
+        }
+    }
+}
+
 pub fn get_pos(tid:u32, context: &ITfContext, range: &ITfRange) -> Result<(i32, i32)> {
     #[implement(ITfEditSession)]
     struct Session<'a> {
@@ -2,10 +2,11 @@ use std::ffi::OsString;
 use log::{trace, warn};
 use windows::{core::GUID, Win32::{Foundation::{BOOL, FALSE, LPARAM, TRUE, WPARAM}, UI::{Input::KeyboardAndMouse::{GetKeyboardState, ToUnicodeEx, VK_CAPITAL, VK_CONTROL, VK_LCONTROL, VK_LSHIFT, VK_MENU, VK_RCONTROL, VK_RSHIFT, VK_SHIFT}, TextServices::{ITfContext, ITfKeyEventSink_Impl}}}};
 use windows::core::Result;
+use crate::conf;
+use crate::keys::{Action, Chord};
 use crate::extend::{CharExt, GUIDExt, OsStrExt2, VKExt};
 use super::{edit_session, TextService, TextServiceInner};
 use Input::*;
-use Shortcut::*;
 //----------------------------------------------------------------------------
 //
 //  A "sink" for key events. From here on the processing begins.
@@ -31,14 +32,35 @@ impl ITfKeyEventSink_Impl for TextService {
     fn OnTestKeyDown(&self, _context: Option<&ITfContext>, wparam: WPARAM, lparam: LPARAM) -> Result<BOOL> {
         trace!("OnTestKeyDown({:#04X})", wparam.0);
         let mut inner = self.write()?;
-        // disable the IME completly when CapsLock is on
+        let chord = chord(wparam.0);
+        inner.shift_tap.key_down(chord.key, chord.ctrl || chord.alt);
+        inner.hide_indicator()?;
+        // predicted words are selected by their labels. any other key dismisses them,
+        // except for the modifiers held for the labels.
+        if !inner.predictions.is_empty() && !is_modifier(wparam.0) {
+            let input = inner.parse_input(wparam.0 as u32, lparam.0 as u32)?;
+            if inner.prediction_index(input).is_some() {
+                return Ok(TRUE);
+            }
+            inner.clear_predictions()?;
+        }
+        // disable the IME completly when CapsLock is on, unless it's configured to type ASCII
         if VK_CAPITAL.is_toggled() {
             inner.abort()?;
-            return Ok(FALSE);
+            if !conf::get().behavior.caps_lock_ascii {
+                return Ok(FALSE);
+            }
+            let input = inner.parse_input(wparam.0 as u32, lparam.0 as u32)?;
+            return Ok(matches!(input, Letter(_)).into());
         }
-        // detect shortcut
-        if let Some(shortcut) = Shortcut::try_from(wparam.0) {
-            return inner.test_shortcut(shortcut);
+        // detect key bindings
+        if let Some(action) = conf::get().keys.get(&chord) {
+            if inner.test_action(action) {
+                return Ok(TRUE);
+            }
+        }
+        if chord.ctrl || chord.alt {
+            return Ok(FALSE);
         }
         let input = inner.parse_input(wparam.0 as u32, lparam.0 as u32)?;
         inner.test_input(input)
@@ -51,26 +73,52 @@ impl ITfKeyEventSink_Impl for TextService {
     fn OnKeyDown(&self, context: Option<&ITfContext>, wparam: WPARAM, lparam: LPARAM) -> Result<BOOL> {
         trace!("OnKeyDown({:#04X})", wparam.0);
         let mut inner = self.write()?;
+        let chord = chord(wparam.0);
+        inner.shift_tap.key_down(chord.key, chord.ctrl || chord.alt);
+        inner.hide_indicator()?;
+        if !inner.predictions.is_empty() && !is_modifier(wparam.0) {
+            let input = inner.parse_input(wparam.0 as u32, lparam.0 as u32)?;
+            if let Some(index) = inner.prediction_index(input) {
+                return inner.accept_prediction(index, context);
+            }
+            inner.clear_predictions()?;
+        }
         if VK_CAPITAL.is_toggled() {
             inner.abort()?;
-            return Ok(FALSE);
+            if !conf::get().behavior.caps_lock_ascii {
+                return Ok(FALSE);
+            }
+            let input = inner.parse_input(wparam.0 as u32, lparam.0 as u32)?;
+            return inner.handle_caps_lock(input, context);
         }
-        if let Some(shortcut) = Shortcut::try_from(wparam.0) {
-            return inner.handle_shortcut(shortcut);
+        if let Some(action) = conf::get().keys.get(&chord) {
+            if inner.test_action(action) {
+                return inner.handle_action(action, context);
+            }
+        }
+        if chord.ctrl || chord.alt {
+            return Ok(FALSE);
         }
         let input = inner.parse_input(wparam.0 as u32, lparam.0 as u32)?;
         inner.handle_input(input, context)
     }
 
-    /// Flip the modifiers back
+    /// Key ups are only eaten when Shift is tapped.
     fn OnTestKeyUp(&self, _context: Option<&ITfContext>, wparam: WPARAM, _lparam: LPARAM) -> Result<BOOL> {
         trace!("OnTestKeyUp({:#04X})", wparam.0);
-        Ok(FALSE)
+        let tapped = self.write()?.shift_tap.key_up(wparam.0 as u32);
+        Ok((tapped && conf::get().behavior.shift_tap).into())
     }
 
     fn OnKeyUp(&self, _context: Option<&ITfContext>, wparam: WPARAM, _lparam: LPARAM) -> Result<BOOL> {
         trace!("OnKeyUp({:#04X})", wparam.0);
-        Ok(FALSE)
+        let mut inner = self.write()?;
+        inner.shift_tap.key_up(wparam.0 as u32);
+        if !inner.shift_tap.take() || !conf::get().behavior.shift_tap {
+            return Ok(FALSE);
+        }
+        inner.toggle_ascii()?;
+        Ok(TRUE)
     }
 
     /// I 've never seen this thing called.
@@ -98,11 +146,15 @@ impl TextServiceInner {
             0x08 => Backspace,
             0x09 => Tab,
             0x0D => Enter,
+            0x1B => Escape,
             0x20 => Space,
+            0x23 => End,
+            0x24 => Home,
             0x25 => Left,
             0x26 => Up,
             0x27 => Right,
             0x28 => Down,
+            0x2E => Delete,
             keycode => unsafe {
                 let mut buf = [0;8];
                 let mut keyboard_state = [0;256];
@@ -117,6 +169,8 @@ impl TextServiceInner {
                 match ch {
                     number @ '0'..='9' => Number(number as usize - '0' as usize),
                     letter @ 'a'..='z' | letter @ 'A'..='Z' => Letter(letter),
+                    // control characters are never typed
+                    ch if ch.is_control() => Unknown(keycode),
                     punct => Punct(punct)
                 }
             }
@@ -125,36 +179,42 @@ impl TextServiceInner {
     }
 }
 
-#[derive(Debug)]
-enum Shortcut {
-    NextSchema,
-    Undefine,
-}
-
-impl Shortcut {
-    fn try_from(key_code: usize) -> Option<Shortcut> {
-        let ctrl = VK_CONTROL.is_down() || VK_LCONTROL.is_down() || VK_RCONTROL.is_down();
-        let alt = VK_MENU.is_down();
-        let shift = VK_SHIFT.is_down() || VK_LSHIFT.is_down() || VK_RSHIFT.is_down();
-        match (ctrl, alt, shift, key_code) {
-            (true, false, true, 0x4E) => Some(NextSchema), // Ctrl + Shift + N
-            (true, ..) | (_, true, ..) => Some(Undefine),
-            _ => None,
-        }
+/// The key pressed along with the modifiers being held, to be looked up in the key bindings.
+fn chord(key_code: usize) -> Chord {
+    Chord {
+        ctrl: VK_CONTROL.is_down() || VK_LCONTROL.is_down() || VK_RCONTROL.is_down(),
+        alt: VK_MENU.is_down(),
+        shift: VK_SHIFT.is_down() || VK_LSHIFT.is_down() || VK_RSHIFT.is_down(),
+        key: key_code as u32,
     }
 }
 
+/// Shift, Ctrl and Alt, either side or not.
+fn is_modifier(key_code: usize) -> bool {
+    matches!(key_code, 0x10..=0x12 | 0xA0..=0xA5)
+}
 
 /// Inputs that are easier to understand and handle.
 /// See https://learn.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes for keycodes.
 #[derive(Debug, Clone, Copy)]
 enum Input {
     Letter(char), Number(usize), Punct(char),
-    Space, Backspace, Enter, Tab,
-    Left, Up, Right, Down,
+    Space, Backspace, Delete, Enter, Tab, Escape,
+    Left, Up, Right, Down, Home, End,
     Unknown(#[allow(dead_code)] u32)
 }
 
+impl Input {
+    /// The character typed, if any.
+    fn char(self) -> Option<char> {
+        match self {
+            Letter(ch) | Punct(ch) => Some(ch),
+            Number(number) => char::from_digit(number as u32, 10),
+            _ => None,
+        }
+    }
+}
+
 //----------------------------------------------------------------------------
 //
 //  After simplifying the overly-complicated events, we can start handling them.
@@ -165,7 +225,9 @@ enum Input {
 impl TextServiceInner {
     fn test_input(&self, input: Input) -> Result<BOOL> {
         trace!("test_input({:?})", input);
-        if self.composition.is_none() {
+        if self.ascii && self.composition.is_none() {
+            Ok(FALSE)
+        } else if self.composition.is_none() {
             match input {
                 Letter(_) | Punct(_) | Space => Ok(TRUE),
                 _ => Ok(FALSE),
@@ -182,45 +244,94 @@ impl TextServiceInner {
             return Ok(FALSE);
         };
         self.context = Some(context.clone());
+        if self.ascii && self.composition.is_none() {
+            return Ok(FALSE);
+        }
         if self.composition.is_none() {
             match input {
                 // letters start compositions. punctuators need to be re-mapped.
                 Letter(letter) => {
                     self.start_composition()?;
-                    self.push(letter)?
+                    self.push(letter)?;
+                    self.auto_commit()?
+                },
+                // the prefixes of the providers start compositions as well
+                Punct(punct) if self.engine.takes("", punct) => {
+                    self.start_composition()?;
+                    self.push(punct)?;
                 },
                 Punct(punct) => {
+                    self.update_context()?;
                     let ch = self.engine.remap_punct(punct);
                     self.insert_char(ch)?
                 },
                 Space => {
+                    self.update_context()?;
                     let ch = self.engine.remap_punct(' ');
                     self.insert_char(ch)?
                 }
                 _ => {return Ok(FALSE)}
             }
         } else {
+            // the prefixes of the providers are typed on, and so are the characters they take
+            if let (Number(_) | Punct(_), Some(ch)) = (input, input.char()) {
+                if self.engine.takes(&self.spelling, ch) {
+                    self.push(ch)?;
+                    return Ok(TRUE);
+                }
+            }
+            // candidates are selected by their labels
+            if let Some(index) = self.label_index(input) {
+                self.select(index)?;
+                return Ok(TRUE);
+            }
             match input {
-                Letter(letter) => self.push(letter)?,
-                Number(0) => (),
-                Number(number) => self.select(number - 1)?,
+                Letter(letter) => {
+                    self.push(letter)?;
+                    self.auto_commit()?
+                },
+                Number(number) => {
+                    let ch = char::from_digit(number as u32, 10).unwrap();
+                    let remmaped = self.engine.remap_punct(ch);
+                    self.force_commit(remmaped)?;
+                },
+                // punctuators that the schema remaps are kept in the spelling if configured so.
+                // '-' and '=' still turn pages if there are pages to turn to.
+                Punct(punct) if conf::get().behavior.compose_puncts && self.engine.remaps(punct) => match punct {
+                    '-' if !self.engine.is_joiner(punct) && self.has_prev_page() => self.prev_page()?,
+                    '=' if !self.engine.is_joiner(punct) && self.has_next_page() => self.next_page()?,
+                    punct => self.push(punct)?,
+                },
                 Punct(punct) => {
                     let remmaped = self.engine.remap_punct(punct);
-                    if remmaped.is_joiner() {
+                    if self.engine.is_joiner(punct) {
                         self.push(punct)?;
+                    } else if punct == '-' && self.has_prev_page() {
+                        self.prev_page()?;
+                    } else if punct == '=' && self.has_next_page() {
+                        self.next_page()?;
                     } else {
                         self.force_commit(remmaped)?;
                     }
                 },
-                Space => self.commit()?,
-                Enter => self.release()?,
+                Space => {
+                    let remmaped = self.engine.remap_punct(' ');
+                    self.force_commit(remmaped)?;
+                },
                 Backspace => self.pop()?,
-                Tab => {
-                    self.push(' ')?;
-                    self.release()?
-                } 
-                // disable cursor movement because I am lazy.
-                Left | Up | Right | Down => (),
+                Delete => self.delete()?,
+                // they're bound to actions by default. unbound, they do nothing to the composition.
+                Enter | Tab | Escape => (),
+                // move the highlight. a focused segment is revised along the way.
+                Up => self.move_highlight(false)?,
+                Down => self.move_highlight(true)?,
+                Left if self.navigating => self.move_highlight(false)?,
+                Right if self.navigating => self.move_highlight(true)?,
+                // move the caret and focus on the segment where the caret is
+                Left => self.step_caret(false)?,
+                Right => self.step_caret(true)?,
+                Home => self.move_caret(0)?,
+                End => self.move_caret(self.spelling.len())?,
                 Unknown(_) => {
                     return Ok(FALSE);
                 }
@@ -229,36 +340,121 @@ impl TextServiceInner {
         return Ok(TRUE);
     }
 
+    /// Entering the ASCII mode releases the spelling as is.
+    fn toggle_ascii(&mut self) -> Result<()> {
+        if self.composition.is_some() {
+            self.release()?;
+        }
+        self.ascii = !self.ascii;
+        Ok(())
+    }
+
+    /// With CapsLock on, letters are typed as ASCII with the case inverted.
+    fn handle_caps_lock(&mut self, input: Input, context: Option<&ITfContext>) -> Result<BOOL> {
+        let (Letter(letter), Some(context)) = (input, context) else {
+            return Ok(FALSE);
+        };
+        self.context = Some(context.clone());
+        let ch = if letter.is_ascii_uppercase() {
+            letter.to_ascii_lowercase()
+        } else {
+            letter.to_ascii_uppercase()
+        };
+        self.insert_char(ch)?;
+        Ok(TRUE)
+    }
+
+    /// The index of the candidate labeled by the input, if any.
+    /// Letters are labels only if they can't be typed into the spelling.
+    fn label_index(&self, input: Input) -> Option<usize> {
+        let ch = input.char()?;
+        if let Letter(_) = input {
+            let mut spelling = self.spelling.clone();
+            spelling.insert(self.caret, ch);
+            if self.engine.recognizes(&spelling) {
+                return None;
+            }
+        }
+        conf::get().layout.label_index(ch)
+    }
+
+    /// The index of the predicted word labeled by the input, if any.
+    fn prediction_index(&self, input: Input) -> Option<usize> {
+        self.label_index(input).filter(|index|*index < self.predictions.len())
+    }
+
+    /// Commit a predicted word, then predict the next one.
+    fn accept_prediction(&mut self, index: usize, context: Option<&ITfContext>) -> Result<BOOL> {
+        let Some(context) = context else {
+            warn!("Context is None");
+            return Ok(FALSE);
+        };
+        self.context = Some(context.clone());
+        let word = self.predictions[index].output.clone();
+        self.insert_str(&word)?;
+        self.predict()?;
+        Ok(TRUE)
+    }
+
     fn insert_char(&mut self, ch: char) -> Result<()> {
         self.char_buf.clear();
         self.char_buf.push(ch);
+        self.insert_buf()
+    }
+
+    fn insert_str(&mut self, str: &str) -> Result<()> {
+        self.char_buf.clear();
+        self.char_buf.push_str(str);
+        self.insert_buf()
+    }
+
+    fn insert_buf(&mut self) -> Result<()> {
         let text = OsString::from(&self.char_buf).wchars();
-        edit_session::insert_text(self.tid, self.context()?, &text)
+        edit_session::insert_text(self.tid, self.context()?, &text)?;
+        self.engine.learn(&self.char_buf);
+        Ok(())
     }
 
-    fn test_shortcut(&self, shortcut: Shortcut) -> Result<BOOL> {
-        if self.composition.is_none() {
-            match shortcut {
-                NextSchema => Ok(TRUE),
-                _ => Ok(FALSE),
-            }
-        } else {
-            Ok(FALSE)
+    /// Schemas are only switched between compositions. The toggles are always available.
+    fn test_action(&self, action: Action) -> bool {
+        trace!("test_action({action})");
+        match action {
+            Action::NextSchema | Action::PrevSchema => self.composition.is_none(),
+            Action::ToggleAscii | Action::ToggleLongGlyph => true,
+            _ => self.composition.is_some(),
         }
     }
 
-    fn handle_shortcut(&mut self, shortcut: Shortcut) -> Result<BOOL> {
-        if self.composition.is_none() {
-            match shortcut {
-                NextSchema => {    
-                    self.engine.next_schema();
-                    Ok(TRUE)
-                }
-                _ => Ok(FALSE),
-            }
-        } else {
-            Ok(FALSE)
+    fn handle_action(&mut self, action: Action, context: Option<&ITfContext>) -> Result<BOOL> {
+        trace!("handle_action({action})");
+        if let Some(context) = context {
+            self.context = Some(context.clone());
         }
+        match action {
+            Action::NextSchema => {
+                self.engine.next_schema();
+                self.indicate_schema()?
+            },
+            Action::PrevSchema => {
+                self.engine.prev_schema();
+                self.indicate_schema()?
+            },
+            // in the sentence mode, words are separated by the commit key and committed by the release key
+            Action::Commit if conf::get().behavior.sentence_mode => self.separate()?,
+            Action::Release if conf::get().behavior.sentence_mode => self.commit_all()?,
+            Action::Commit => self.commit()?,
+            Action::Release => self.release()?,
+            Action::ReleaseWithSpace => {
+                self.move_caret(self.spelling.len())?;
+                self.push(' ')?;
+                self.release()?
+            },
+            Action::Abort => self.discard()?,
+            Action::ToggleAscii => self.toggle_ascii()?,
+            Action::ToggleLongGlyph => self.engine.toggle_long_glyph(),
+            Action::PrevPage => self.prev_page()?,
+            Action::NextPage => self.next_page()?,
+        }
+        Ok(TRUE)
     }
 }
-
@@ -12,7 +12,7 @@ use parking_lot::{RwLock, RwLockWriteGuard};
 use log::{debug, error, warn};
 
 use windows::{core::{implement, AsImpl, Interface, Result, VARIANT}, Win32::{Foundation::E_FAIL, UI::{TextServices::{ITfComposition, ITfCompositionSink, ITfContext, ITfDisplayAttributeProvider, ITfKeyEventSink, ITfLangBarItem, ITfTextInputProcessor, ITfTextInputProcessorEx, ITfThreadMgr, ITfThreadMgrEventSink, HKL}, WindowsAndMessaging::HICON}}};
-use crate::{engine::{Engine, Suggestion}, global::registered_hkl, ui::candidate_list::CandidateList};
+use crate::{engine::{Engine, Suggestion}, global::registered_hkl, keys::ShiftTap, ui::candidate_list::CandidateList};
 
 //----------------------------------------------------------------------------
 //
@@ -51,12 +51,25 @@ struct TextServiceInner {
     // KeyEventSink
     hkl: HKL,
     char_buf: String,
+    ascii: bool,
+    shift_tap: ShiftTap,
     // Composition
     composition: Option<ITfComposition>,
     spelling: String,
+    caret: usize,
     selected: String,
     suggestions: Vec<Suggestion>,
+    page: usize,
+    highlight: usize,
+    navigating: bool,
     preedit: String,
+    // the segment of the 1st suggestion being revised and the suggestions for it
+    focus: Option<usize>,
+    segment_suggestions: Vec<Suggestion>,
+    // the words predicted after a commit, shown while not composing
+    predictions: Vec<Suggestion>,
+    // the name of the schema shown after switching, till the next key
+    indicating: bool,
     // display attribute provider
     display_attribute: Option<VARIANT>,
     // UI
@@ -76,12 +89,22 @@ impl TextService {
             context: None,
             hkl: registered_hkl()?,
             char_buf: String::with_capacity(4),
+            ascii: false,
+            shift_tap: ShiftTap::default(),
             cookie: None,
             composition: None,
             spelling: String::with_capacity(32),
+            caret: 0,
             suggestions: Vec::new(),
+            page: 0,
+            highlight: 0,
+            navigating: false,
             selected: String::with_capacity(32),
             preedit: String::with_capacity(32),
+            focus: None,
+            segment_suggestions: Vec::new(),
+            predictions: Vec::new(),
+            indicating: false,
             icon: HICON::default(),
             candidate_list: None,
             display_attribute: None,
@@ -1,5 +1,6 @@
 use windows::Win32::UI::TextServices::{ITfThreadMgrEventSink_Impl, ITfDocumentMgr, ITfContext};
-use windows::core::Result;
+use windows::core::{Interface, Result};
+use crate::engine::DocId;
 use super::TextService;
 
 #[allow(non_snake_case, unused)]
@@ -8,10 +9,23 @@ impl ITfThreadMgrEventSink_Impl for TextService {
         Ok(())
     }
     fn OnUninitDocumentMgr(&self, pdim: Option<&ITfDocumentMgr>) -> Result<()> {
+        if let Some(pdim) = pdim {
+            self.write()?.engine.forget(doc_id(pdim));
+        }
         Ok(())
     }
     fn OnSetFocus(&self, focus: Option<&ITfDocumentMgr>, prevfocus: Option<&ITfDocumentMgr>) ->Result<()> {
-        self.write()?.abort()
+        let mut inner = self.write()?;
+        inner.abort()?;
+        inner.engine.focus(focus.map(doc_id));
+        if let Some(focus) = focus {
+            // the indicator is shown at the caret of the newly focused document
+            if let Ok(context) = unsafe { focus.GetTop() } {
+                inner.context = Some(context);
+            }
+            inner.focus_app()?;
+        }
+        Ok(())
     }
     fn OnPushContext(&self, pic: Option<&ITfContext>) -> Result<()> {
         Ok(())
@@ -19,4 +33,9 @@ impl ITfThreadMgrEventSink_Impl for TextService {
     fn OnPopContext(&self, pic: Option<&ITfContext>) -> Result<()> {
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Document managers are told apart by their addresses, which are unique while they live.
+fn doc_id(document_mgr: &ITfDocumentMgr) -> DocId {
+    document_mgr.as_raw() as DocId
+}
@@ -2,7 +2,7 @@ use std::{cmp::max, ffi::{CString, OsString}, mem::{self, size_of, ManuallyDrop}
 use log::{trace, debug, error};
 use windows::{Win32::{UI::WindowsAndMessaging::{CreateWindowExA, DefWindowProcA, DestroyWindow, GetWindowLongPtrA, LoadCursorW, RegisterClassExA, SetWindowLongPtrA, SetWindowPos, ShowWindow, CS_DROPSHADOW, CS_HREDRAW, CS_IME, CS_VREDRAW, HICON, HWND_TOPMOST, IDC_ARROW, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SW_HIDE, SW_SHOWNOACTIVATE, WINDOW_LONG_PTR_INDEX, WM_PAINT, WNDCLASSEXA, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_POPUP}, Foundation::{GetLastError, BOOL, HWND, LPARAM, LRESULT, RECT, SIZE, WPARAM}, Graphics::Gdi::{self, BeginPaint, CreateFontA, EndPaint, GetDC, GetDeviceCaps, GetTextExtentPoint32W, InvalidateRect, ReleaseDC, SelectObject, SetBkMode, SetTextColor, TextOutW, HDC, HFONT, LOGPIXELSY, OUT_TT_PRECIS, PAINTSTRUCT, TRANSPARENT}}, core::{s, PCSTR}};
 use windows::core::Result;
-use crate::{conf::{self}, engine::Suggestion, extend::OsStrExt2, global, CANDI_INDEXES, CANDI_INDEX_SUFFIX, CANDI_INDEX_SUFFIX_MONO};
+use crate::{conf::{self}, engine::Suggestion, extend::OsStrExt2, global, CANDI_INDEX_SUFFIX, CANDI_INDEX_SUFFIX_MONO};
 
 use super::Color;
 
@@ -135,22 +135,36 @@ impl CandidateList {
         Ok(())
     }
 
-    pub fn show(&self, suggs: &Vec<Suggestion>) -> Result<()> {
+    /// Show the suggestions with the one at `highlight` highlighted.
+    pub fn show(&self, suggs: &[Suggestion], highlight: usize) -> Result<()> {
+        let mut labels = conf::get().layout.labels.chars();
+        let rows: Vec<(String, &str)> = suggs.iter()
+            .map(|sugg|(format!("{}{}", labels.next().unwrap_or(' '), self.index_suffix), sugg.output.as_str()))
+            .collect();
+        self.show_rows(&rows, highlight)
+    }
+
+    /// Show a message, like the name of the schema, as a single row without label.
+    pub fn show_message(&self, message: &str) -> Result<()> {
+        self.show_rows(&[(String::new(), message)], 0)
+    }
+
+    /// Show the rows of labels and texts.
+    fn show_rows(&self, rows: &[(String, &str)], highlight: usize) -> Result<()> {
         unsafe{ 
             let conf = conf::get();
-            let mut indice = Vec::with_capacity(suggs.len());
-            let mut candis = Vec::with_capacity(suggs.len());
+            let mut indice = Vec::with_capacity(rows.len());
+            let mut candis = Vec::with_capacity(rows.len());
 
             let mut candi_height: i32 = 0;
             let mut index_height: i32 = 0;
             let mut index_width: i32 = 0;
             let mut candi_width: i32 = 0;
-            let mut candi_widths = Vec::with_capacity(suggs.len());
+            let mut candi_widths = Vec::with_capacity(rows.len());
                 
             let dc: HDC = GetDC(self.window);   
-            for (index, sugg) in suggs.iter().enumerate() {
+            for (index, text) in rows.iter() {
                 let mut size = SIZE::default();
-                let index = format!("{}{}", CANDI_INDEXES[index], self.index_suffix);
                 let index = OsString::from(index).wchars();
                 SelectObject(dc, self.index_font);
                 GetTextExtentPoint32W(dc, &index, &mut size);
@@ -158,7 +172,7 @@ impl CandidateList {
                 index_width = max(index_width, size.cx);
                 indice.push(index);
 
-                let candi = OsString::from(&sugg.output).wchars();
+                let candi = OsString::from(text).wchars();
                 SelectObject(dc, self.candi_font);
                 GetTextExtentPoint32W(dc, &candi, &mut size);
                 candi_height = max(candi_height, size.cy);
@@ -172,7 +186,7 @@ impl CandidateList {
             let mut wnd_height = 0;
             let mut wnd_width = 0;
             if conf.layout.vertical {
-                let candi_num: i32 = suggs.len().try_into().unwrap();
+                let candi_num: i32 = rows.len().try_into().unwrap();
                 wnd_height += candi_num * label_height;
                 wnd_width += CLIP_WIDTH + LABEL_PADDING_LEFT + index_width + candi_width + LABEL_PADDING_RIGHT;
                 wnd_width = max(wnd_width, wnd_height * 4 / 5)
@@ -188,15 +202,16 @@ impl CandidateList {
             wnd_height += BORDER_WIDTH * 2;
             wnd_width += BORDER_WIDTH * 2;
 
+            let highlight = highlight.min(rows.len() - 1);
             let highlight_width = if conf.layout.vertical {
                 wnd_width - CLIP_WIDTH - BORDER_WIDTH * 2
             } else {
-                LABEL_PADDING_LEFT + index_width + candi_widths[0] + LABEL_PADDING_RIGHT   
+                LABEL_PADDING_LEFT + index_width + candi_widths[highlight] + LABEL_PADDING_RIGHT   
             };
 
             // passing extra args to WndProc
             let arg = PaintArg {
-                wnd_width, wnd_height, highlight_width, 
+                wnd_width, wnd_height, highlight, highlight_width, 
                 label_height, row_height,
                 index_width, index_height, 
                 candi_widths: candi_widths.clone(), candi_height,
@@ -230,6 +245,7 @@ impl CandidateList {
 struct PaintArg {
     wnd_width: i32,
     wnd_height: i32,
+    highlight: usize,
     highlight_width: i32,
     label_height: i32,
     row_height: i32,
@@ -269,38 +285,49 @@ fn paint(window: HWND) -> LRESULT {
         error!("BeginPaint failed.");
         return LRESULT::default();
     }
+    // where the highlight is
+    let mut highlight_x = BORDER_WIDTH + CLIP_WIDTH;
+    let mut highlight_y = BORDER_WIDTH;
+    for i in 0..arg.highlight {
+        if conf.layout.vertical {
+            highlight_y += arg.label_height;
+        } else {
+            highlight_x += arg.index_width + arg.candi_widths[i] + LABEL_PADDING_LEFT + LABEL_PADDING_RIGHT;
+        }
+    }
     unsafe {
-    // window
+        // window
         FillRect(dc, 0, 0, arg.wnd_width, arg.wnd_height, conf.color.background);
         // clip
-        FillRect(dc, BORDER_WIDTH, BORDER_WIDTH, CLIP_WIDTH, arg.label_height, conf.color.clip);
+        FillRect(dc, BORDER_WIDTH, highlight_y, CLIP_WIDTH, arg.label_height, conf.color.clip);
         // highlight
-        FillRect(dc, BORDER_WIDTH + CLIP_WIDTH, BORDER_WIDTH, arg.highlight_width, arg.label_height, conf.color.highlight);
+        FillRect(dc, highlight_x, highlight_y, arg.highlight_width, arg.label_height, conf.color.highlight);
     }
 
-    // highlighted text
+    // text
     let mut index_x = BORDER_WIDTH + CLIP_WIDTH + LABEL_PADDING_LEFT;
     let mut candi_x = BORDER_WIDTH + index_x + arg.index_width;
     let mut index_y = BORDER_WIDTH + LABEL_PADDING_TOP + (arg.row_height - arg.index_height) / 2;
     let mut candi_y = BORDER_WIDTH + LABEL_PADDING_TOP + (arg.row_height - arg.candi_height) / 2;
-    unsafe {
-        SetBkMode(dc, TRANSPARENT);
-        TextOut(dc, index_x, index_y, &arg.indice[0], conf.color.index, arg.index_font);
-        TextOut(dc, candi_x, candi_y, &arg.candis[0], conf.color.highlighted, arg.candi_font);
-
-    }
-    // normal text
-    for i in 1..arg.candis.len() {
-        if conf.layout.vertical {
-            index_y += arg.label_height;
-            candi_y += arg.label_height;
-        } else {
-            index_x += arg.index_width + arg.candi_widths[i - 1] + LABEL_PADDING_LEFT + LABEL_PADDING_RIGHT;
-            candi_x += arg.index_width + arg.candi_widths[i - 1] + LABEL_PADDING_LEFT + LABEL_PADDING_RIGHT;
+    unsafe { SetBkMode(dc, TRANSPARENT) };
+    for i in 0..arg.candis.len() {
+        if i != 0 {
+            if conf.layout.vertical {
+                index_y += arg.label_height;
+                candi_y += arg.label_height;
+            } else {
+                index_x += arg.index_width + arg.candi_widths[i - 1] + LABEL_PADDING_LEFT + LABEL_PADDING_RIGHT;
+                candi_x += arg.index_width + arg.candi_widths[i - 1] + LABEL_PADDING_LEFT + LABEL_PADDING_RIGHT;
+            }
         }
+        let color = if i == arg.highlight {
+            conf.color.highlighted
+        } else {
+            conf.color.candidate
+        };
         unsafe {
             TextOut(dc, index_x, index_y, &arg.indice[i], conf.color.index, arg.index_font);
-            TextOut(dc, candi_x, candi_y, &arg.candis[i], conf.color.candidate, arg.candi_font);
+            TextOut(dc, candi_x, candi_y, &arg.candis[i], color, arg.candi_font);
         }
     }
     unsafe {
@@ -326,7 +353,7 @@ unsafe fn FillRect(hdc: HDC, x: i32, y: i32, width: i32, height: i32, color: u32
         left: x,
         top: y,
         right: x + width,
-        bottom: height,
+        bottom: y + height,
     };
     unsafe { Gdi::FillRect(hdc, &rect, color.to_hbrush()) };
 }